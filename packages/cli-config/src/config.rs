@@ -1,5 +1,6 @@
 use crate::BundleConfig;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -316,22 +317,149 @@ pub struct WebProxyConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebWatcherConfig {
+    /// Paths to watch for changes, relative to the crate root.
+    ///
+    /// Entries may be glob patterns like `src/**/*.rs` or `assets/*.css`.
     #[serde(default = "watch_path_default")]
     pub watch_path: Vec<PathBuf>,
 
+    /// Paths to exclude from the watcher, using `.gitignore` syntax (e.g. `src/generated/**`).
+    ///
+    /// Excluded paths take precedence over `watch_path`.
+    #[serde(default)]
+    pub exclude_path: Vec<String>,
+
+    /// Whether to exclude the directories the watcher skips by default (`target`, `node_modules`, `.git`,
+    /// the output directory, etc). Turn this off to hot reload rsx generated into one of them.
+    #[serde(default = "true_bool")]
+    pub default_excludes: bool,
+
+    /// Whether to skip the swap, lock, and temp files common editors write next to the file being edited (vim
+    /// `.swp` files, emacs `#autosave#` files, JetBrains `___jb_tmp___` files, ...). Turn this off if one of
+    /// your files happens to use one of those names.
+    #[serde(default = "true_bool")]
+    pub default_editor_ignores: bool,
+
+    /// Whether paths listed in the crate's `.gitignore` should be excluded from the watcher
+    #[serde(default = "true_bool")]
+    pub respect_gitignore: bool,
+
     #[serde(default)]
     pub reload_html: bool,
 
     #[serde(default = "true_bool")]
     pub index_on_404: bool,
+
+    /// How long the file watcher waits for changes to settle before processing them, in milliseconds.
+    ///
+    /// The window is shared by every file: any new event restarts it, and everything that changed is handled as one
+    /// batch once the watcher has been quiet for this long. Repeated events for the same file are coalesced.
+    #[serde(default = "debounce_ms_default")]
+    pub debounce_ms: u64,
+
+    /// How many distinct files need to change in one batch for it to count as an event storm, like a
+    /// `git checkout` rewriting a large part of the project.
+    #[serde(default = "storm_threshold_default")]
+    pub storm_threshold: usize,
+
+    /// How long the file watcher waits for an event storm to settle before processing it, in milliseconds.
+    ///
+    /// The whole storm is handled as one batch, so it triggers at most one rebuild.
+    #[serde(default = "storm_debounce_ms_default")]
+    pub storm_debounce_ms: u64,
+
+    /// The longest the file watcher keeps debouncing a batch of changes, in milliseconds.
+    ///
+    /// Something that writes files constantly (like a log file or build output) would otherwise keep the
+    /// watcher waiting forever. Once this much time has passed since the first change, the batch is processed
+    /// even if events are still arriving.
+    #[serde(default = "max_debounce_ms_default")]
+    pub max_debounce_ms: u64,
+
+    /// The longest the file watcher waits for changed files to stop being written before reading them, in
+    /// milliseconds. Every file in a batch of changes shares this budget.
+    #[serde(default = "max_settle_ms_default")]
+    pub max_settle_ms: u64,
+
+    /// Whether to also watch and hot reload the workspace crates this crate depends on by path.
+    ///
+    /// This lets rsx in component libraries that live next to the app in the same workspace hot reload. It is
+    /// on by default, so dx serve also watches the `src` directories of those crates. Set it to `false` to only
+    /// watch the app crate, as older versions of the CLI did.
+    #[serde(default = "true_bool")]
+    pub watch_workspace: bool,
+
+    /// Rebuild on any save to `Cargo.toml`, not just the ones that change dependencies, features, profiles,
+    /// or targets.
+    #[serde(default)]
+    pub rebuild_on_any_manifest_change: bool,
+
+    /// Rebuild the app when a css file changes instead of reloading stylesheets in place. Turn this on if your
+    /// css is compiled into the app, e.g. by a build script.
+    #[serde(default)]
+    pub rebuild_on_css_change: bool,
+
+    /// Rebuild the app when `Cargo.lock` changes, e.g. after `cargo update` or pulling a teammate's lockfile.
+    /// Turn this off if you update your dependencies often and don't need the running app to follow along.
+    #[serde(default = "true_bool")]
+    pub rebuild_on_lockfile_change: bool,
+
+    /// How to handle changes to files with a given extension, overriding the defaults.
+    ///
+    /// By default `.rs` files are hot reloaded as rsx, and css and anything in the asset directory is reloaded
+    /// as an asset. For example, `extensions = { md = "rebuild", log = "ignore" }`.
+    #[serde(default)]
+    pub extensions: HashMap<String, ExtensionPolicy>,
+
+    /// More directories of assets to watch, relative to the crate, like images or fonts.
+    ///
+    /// Unlike other watched paths, every change in these is sent to clients as an asset update, whatever the
+    /// file's extension.
+    #[serde(default)]
+    pub asset_dirs: Vec<PathBuf>,
+
+    /// Assets larger than this many bytes are sent to clients without a content hash, so we don't read them
+    /// into memory on every change.
+    #[serde(default = "max_asset_hash_size_default")]
+    pub max_asset_hash_size: u64,
+}
+
+/// What the file watcher does when a file with a given extension changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtensionPolicy {
+    /// Try to hot reload the rsx in the file, rebuilding if the change can't be hot reloaded
+    Rsx,
+    /// Tell clients to reload the file in place
+    Asset,
+    /// Rebuild the app
+    Rebuild,
+    /// Don't do anything
+    Ignore,
 }
 
 impl Default for WebWatcherConfig {
     fn default() -> Self {
         Self {
             watch_path: watch_path_default(),
+            exclude_path: Vec::new(),
+            default_excludes: true,
+            default_editor_ignores: true,
+            respect_gitignore: true,
             reload_html: false,
             index_on_404: true,
+            debounce_ms: debounce_ms_default(),
+            storm_threshold: storm_threshold_default(),
+            storm_debounce_ms: storm_debounce_ms_default(),
+            max_debounce_ms: max_debounce_ms_default(),
+            max_settle_ms: max_settle_ms_default(),
+            watch_workspace: true,
+            rebuild_on_any_manifest_change: false,
+            rebuild_on_css_change: false,
+            rebuild_on_lockfile_change: true,
+            extensions: HashMap::new(),
+            asset_dirs: vec![],
+            max_asset_hash_size: max_asset_hash_size_default(),
         }
     }
 }
@@ -340,6 +468,30 @@ fn watch_path_default() -> Vec<PathBuf> {
     vec![PathBuf::from("src"), PathBuf::from("examples")]
 }
 
+fn debounce_ms_default() -> u64 {
+    50
+}
+
+fn storm_threshold_default() -> usize {
+    50
+}
+
+fn storm_debounce_ms_default() -> u64 {
+    1000
+}
+
+fn max_debounce_ms_default() -> u64 {
+    5000
+}
+
+fn max_settle_ms_default() -> u64 {
+    200
+}
+
+fn max_asset_hash_size_default() -> u64 {
+    16 * 1024 * 1024
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct WebResourceConfig {
     pub dev: WebDevResourceConfig,
@@ -38,9 +38,13 @@ pub struct ServeArguments {
     #[clap(long)]
     pub args: Vec<String>,
 
-    /// Sets the interval in seconds that the CLI will poll for file changes on WSL.
+    /// Sets the interval in seconds that the CLI will poll for file changes on WSL or with `--poll-watcher`.
     #[clap(long, default_missing_value = "2")]
     pub wsl_file_poll_interval: Option<u16>,
+
+    /// Poll for file changes instead of using native filesystem events. Useful on network filesystems and docker bind mounts [default: false]
+    #[arg(long, default_missing_value="true", num_args=0..=1)]
+    pub poll_watcher: Option<bool>,
 }
 
 /// Run the WASM project on dev-server
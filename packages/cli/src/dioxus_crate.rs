@@ -3,10 +3,11 @@ use dioxus_cli_config::{DioxusConfig, Platform};
 use krates::cm::Target;
 use krates::{cm::TargetKind, Cmd, Krates, NodeId};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::{
     fmt::{Display, Formatter},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use crate::metadata::CargoError;
@@ -79,6 +80,65 @@ fn load_dioxus_config(
     }
 }
 
+// Find the workspace members a package depends on by path, directly or through another workspace member
+fn workspace_dependency_dirs(krates: &Krates, package: NodeId) -> Vec<PathBuf> {
+    let members: HashMap<PathBuf, &krates::cm::Package> = krates
+        .workspace_members()
+        .filter_map(|node| match node {
+            krates::Node::Krate { krate, .. } => Some((
+                krate.manifest_path.parent()?.as_std_path().to_path_buf(),
+                krate,
+            )),
+            _ => None,
+        })
+        .collect();
+
+    let package = &krates[package];
+    let crate_dir = package.manifest_path.parent().unwrap().as_std_path();
+    let mut found = vec![];
+    let mut queue = vec![package];
+    while let Some(package) = queue.pop() {
+        for dep in &package.dependencies {
+            let Some(path) = dep.path.as_ref() else {
+                continue;
+            };
+            let path = path.as_std_path().to_path_buf();
+            if path == crate_dir || found.contains(&path) {
+                continue;
+            }
+            if let Some(member) = members.get(&path) {
+                queue.push(*member);
+                found.push(path);
+            }
+        }
+    }
+
+    found
+}
+
+/// Make the target dir cargo metadata reports comparable with the absolute paths the watcher reports.
+///
+/// Cargo already applies `CARGO_TARGET_DIR` and `build.target-dir`, but a relative `CARGO_TARGET_DIR` is only joined
+/// onto the directory cargo ran in, leaving `..` components behind.
+fn resolve_target_dir(metadata_target_dir: &Path) -> PathBuf {
+    if let Ok(dir) = metadata_target_dir.canonicalize() {
+        return dir;
+    }
+
+    // The target dir doesn't exist before the first build, so resolve the components by hand
+    let mut dir = PathBuf::new();
+    for component in metadata_target_dir.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                dir.pop();
+            }
+            component => dir.push(component),
+        }
+    }
+    dir
+}
+
 // Find the main package in the workspace
 fn find_main_package(package: Option<String>, krates: &Krates) -> Result<NodeId, CrateConfigError> {
     let kid = match package {
@@ -136,6 +196,7 @@ pub struct DioxusCrate {
     pub package: NodeId,
     pub dioxus_config: DioxusConfig,
     pub target: Target,
+    metadata_target_dir: PathBuf,
 }
 
 impl DioxusCrate {
@@ -143,7 +204,11 @@ impl DioxusCrate {
         let mut cmd = Cmd::new();
         cmd.features(target.features.clone());
         let builder = krates::Builder::new();
-        let krates = builder.build(cmd, |_| {})?;
+        let metadata = krates::cm::MetadataCommand::from(cmd)
+            .exec()
+            .map_err(krates::Error::from)?;
+        let metadata_target_dir = metadata.target_directory.clone().into_std_path_buf();
+        let krates = builder.build_with_metadata(metadata, |_| {})?;
         let package = find_main_package(target.package.clone(), &krates)?;
 
         let dioxus_config = load_dioxus_config(&krates, package)?.unwrap_or_default();
@@ -174,6 +239,7 @@ impl DioxusCrate {
             package,
             dioxus_config,
             target,
+            metadata_target_dir,
         })
     }
 
@@ -207,6 +273,20 @@ impl DioxusCrate {
             .to_path_buf()
     }
 
+    /// Get the directory cargo puts build artifacts in, including the `OUT_DIR`s of build scripts.
+    ///
+    /// This respects `CARGO_TARGET_DIR` and `build.target-dir` in the cargo config, so it may be outside the
+    /// workspace entirely.
+    pub fn target_dir(&self) -> PathBuf {
+        resolve_target_dir(&self.metadata_target_dir)
+    }
+
+    /// Get the directories of the workspace members this crate depends on by path, directly or through
+    /// another workspace member
+    pub fn workspace_dependency_dirs(&self) -> Vec<PathBuf> {
+        workspace_dependency_dirs(&self.krates, self.package)
+    }
+
     /// Get the main source file of the target
     pub fn main_source_file(&self) -> PathBuf {
         self.target.src_path.as_std_path().to_path_buf()
@@ -352,3 +432,74 @@ impl Display for CrateConfigError {
 }
 
 impl std::error::Error for CrateConfigError {}
+
+#[test]
+fn test_workspace_dependency_dirs() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+
+    let write = |path: &str, contents: &str| {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    };
+    let package = |name: &str, deps: &str| {
+        format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n{deps}")
+    };
+
+    // app -> ui -> icons, plus a member the app doesn't depend on
+    write(
+        "Cargo.toml",
+        "[workspace]\nmembers = [\"app\", \"ui\", \"icons\", \"other\"]\nresolver = \"2\"\n",
+    );
+    write(
+        "app/Cargo.toml",
+        &package("app", "ui = { path = \"../ui\" }\n"),
+    );
+    write("app/src/main.rs", "fn main() {}");
+    write(
+        "ui/Cargo.toml",
+        &package("ui", "icons = { path = \"../icons\" }\n"),
+    );
+    write("ui/src/lib.rs", "");
+    write("icons/Cargo.toml", &package("icons", ""));
+    write("icons/src/lib.rs", "");
+    write("other/Cargo.toml", &package("other", ""));
+    write("other/src/lib.rs", "");
+
+    let mut cmd = Cmd::new();
+    cmd.manifest_path(root.join("Cargo.toml"));
+    let krates = krates::Builder::new().build(cmd, |_| {}).unwrap();
+    let app = find_main_package(Some("app".to_string()), &krates).unwrap();
+
+    let mut dirs = workspace_dependency_dirs(&krates, app);
+    dirs.sort();
+    assert_eq!(dirs, vec![root.join("icons"), root.join("ui")]);
+}
+
+#[test]
+fn test_resolve_target_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let app = root.join("packages/app");
+    std::fs::create_dir_all(&app).unwrap();
+    std::fs::create_dir_all(root.join("build")).unwrap();
+
+    // `CARGO_TARGET_DIR=../../build` run from the app crate
+    assert_eq!(
+        resolve_target_dir(&app.join("../../build")),
+        root.join("build")
+    );
+
+    // The same, before the first build created the directory
+    assert_eq!(
+        resolve_target_dir(&app.join("./../../cache/target")),
+        root.join("cache/target")
+    );
+
+    // Absolute target dirs are used as they are
+    assert_eq!(
+        resolve_target_dir(&root.join("target")),
+        root.join("target")
+    );
+}
@@ -46,6 +46,12 @@ pub enum Error {
     #[error("Error proxying request: {0}")]
     ProxyRequestError(hyper::Error),
 
+    #[error("Failed to create file watcher: {0}\nEnsure you have the required permissions to watch the specified directories.")]
+    WatcherError(#[from] notify::Error),
+
+    #[error("Failed to watch any of the project's paths:\n{0}")]
+    WatchPathsFailed(String),
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
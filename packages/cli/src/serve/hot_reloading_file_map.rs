@@ -6,7 +6,7 @@ use dioxus_rsx::{
 use krates::cm::MetadataCommand;
 use krates::Cmd;
 pub use std::collections::HashMap;
-use std::{ffi::OsStr, path::PathBuf};
+use std::path::PathBuf;
 pub use std::{fs, io, path::Path};
 pub use std::{fs::File, io::Read};
 use syn::spanned::Spanned;
@@ -18,6 +18,26 @@ pub struct FileMap {
     pub errors: Vec<io::Error>,
 
     pub in_workspace: HashMap<PathBuf, Option<PathBuf>>,
+
+    /// The files each source file pulls in through `include_str!`, `include_bytes!`, or `asset!`.
+    ///
+    /// This is refreshed whenever the source file is parsed.
+    pub includes: HashMap<PathBuf, Vec<IncludedFile>>,
+
+    /// The rsx blocks that failed to parse the last time each file was updated.
+    ///
+    /// The other blocks in the file are still hot reloaded.
+    pub rsx_errors: HashMap<PathBuf, Vec<syn::Error>>,
+}
+
+/// A file that a source file depends on through a macro
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncludedFile {
+    pub path: PathBuf,
+
+    /// Whether the contents are compiled into the binary (`include_str!` and `include_bytes!`), rather than
+    /// referenced as an asset
+    pub embedded: bool,
 }
 
 /// A cached file that has been parsed
@@ -29,17 +49,6 @@ pub struct CachedSynFile {
 }
 
 impl FileMap {
-    /// Create a new FileMap from a crate directory
-    ///
-    /// TODO: this should be created with a gitignore filter
-    pub fn create<Ctx: HotReloadingContext>(path: PathBuf) -> io::Result<FileMap> {
-        Self::create_with_filter::<Ctx>(path, |p| {
-            // skip some stuff we know is large by default
-            p.file_name() == Some(OsStr::new("target"))
-                || p.file_name() == Some(OsStr::new("node_modules"))
-        })
-    }
-
     /// Create a new FileMap from a crate directory
     ///
     /// Takes a filter that when returns true, the file will be filtered out (ie not tracked)
@@ -54,6 +63,8 @@ impl FileMap {
             map,
             errors,
             in_workspace: HashMap::new(),
+            includes: HashMap::new(),
+            rsx_errors: HashMap::new(),
         };
 
         map.load_assets::<Ctx>(crate_dir.as_path());
@@ -71,6 +82,14 @@ impl FileMap {
         }
     }
 
+    /// Take over the files tracked by another map, like the map of a workspace member crate
+    pub fn merge(&mut self, other: FileMap) {
+        self.map.extend(other.map);
+        self.errors.extend(other.errors);
+        self.includes.extend(other.includes);
+        self.rsx_errors.extend(other.rsx_errors);
+    }
+
     /// Insert a file into the map and force a full rebuild
     fn full_rebuild(&mut self, file_path: PathBuf, src: String) -> HotreloadError {
         let cached_file = CachedSynFile {
@@ -82,38 +101,67 @@ impl FileMap {
         HotreloadError::Notreloadable
     }
 
+    /// Stop tracking a file, returning the locations of any templates we hot reloaded from it
+    pub fn remove_file(&mut self, file_path: &Path) -> Vec<String> {
+        self.includes.remove(file_path);
+        self.rsx_errors.remove(file_path);
+        self.map
+            .remove(file_path)
+            .map(|cached| cached.templates.into_keys().collect())
+            .unwrap_or_default()
+    }
+
+    /// Find a source file that includes `path` through a macro, along with how it's included
+    pub fn includer_of(&self, path: &Path) -> Option<(&Path, &IncludedFile)> {
+        self.includes.iter().find_map(|(source, includes)| {
+            let include = includes.iter().find(|include| include.path == path)?;
+            Some((source.as_path(), include))
+        })
+    }
+
+    /// Get the tracked files that live under a directory, sorted by path
+    pub fn files_under(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self
+            .map
+            .keys()
+            .filter(|file| file.starts_with(dir))
+            .cloned()
+            .collect();
+        files.sort();
+        files
+    }
+
     /// Try to update the rsx in a file
     pub fn update_rsx<Ctx: HotReloadingContext>(
         &mut self,
         file_path: &Path,
         crate_dir: &Path,
     ) -> Result<Vec<HotReloadTemplateWithLocation>, HotreloadError> {
+        self.rsx_errors.remove(file_path);
         let src = std::fs::read_to_string(file_path)?;
 
         // If we can't parse the contents we want to pass it off to the build system to tell the user that there's a syntax error
-        let syntax = syn::parse_file(&src).map_err(|_err| HotreloadError::Parse)?;
+        let syntax = syn::parse_file(&src).map_err(HotreloadError::Parse)?;
+
+        // The file may have started or stopped including other files
+        self.includes.insert(
+            file_path.to_path_buf(),
+            find_includes(&src, file_path, crate_dir),
+        );
 
         let in_workspace = self.child_in_workspace(crate_dir)?;
 
         // Get the cached file if it exists, otherwise try to create it
         let Some(old_cached) = self.map.get_mut(file_path) else {
-            // if this is a new file, rebuild the project
-            let mut map = FileMap::create::<Ctx>(crate_dir.to_path_buf())?;
-
-            if let Some(err) = map.errors.pop() {
-                return Err(HotreloadError::Failure(err));
-            }
-
-            // merge the new map into the old map
-            self.map.extend(map.map);
-
-            return Err(HotreloadError::Notreloadable);
+            // if this is a new file, start tracking it and rebuild the project. The watcher already ran the path
+            // through its ignore rules, and the other files we track keep the templates they hot reloaded
+            return Err(self.full_rebuild(file_path.to_path_buf(), src));
         };
 
         // If the cached file is not a valid rsx file, rebuild the project, forcing errors
         // TODO: in theory the error is simply in the RsxCallbody. We could attempt to parse it using partial expansion
         // And collect out its errors instead of giving up to a full rebuild
-        let old = syn::parse_file(&old_cached.raw).map_err(|_e| HotreloadError::Parse)?;
+        let old = syn::parse_file(&old_cached.raw).map_err(HotreloadError::Parse)?;
 
         let instances = match diff_rsx(&syntax, &old) {
             // If the changes were just some rsx, we can just update the template
@@ -129,18 +177,27 @@ impl FileMap {
         };
 
         let mut out_templates = vec![];
+        let mut rsx_errors = vec![];
 
         for calls in instances.into_iter() {
             let ChangedRsx { old, new } = calls;
 
             let old_start = old.span().start();
 
-            let old_parsed = syn::parse2::<CallBody>(old.tokens);
-            let new_parsed = syn::parse2::<CallBody>(new.tokens);
-            let (Ok(old_call_body), Ok(new_call_body)) = (old_parsed, new_parsed) else {
+            let Ok(old_call_body) = syn::parse2::<CallBody>(old.tokens) else {
                 continue;
             };
 
+            // Remember broken rsx so the user can be pointed at it, but keep hot reloading the other blocks. The
+            // error's span points into the new file because the tokens came from parsing it
+            let new_call_body = match syn::parse2::<CallBody>(new.tokens) {
+                Ok(body) => body,
+                Err(err) => {
+                    rsx_errors.push(err);
+                    continue;
+                }
+            };
+
             // if the file!() macro is invoked in a workspace, the path is relative to the workspace root, otherwise it's relative to the crate root
             // we need to check if the file is in a workspace or not and strip the prefix accordingly
             let prefix = match in_workspace {
@@ -196,6 +253,10 @@ impl FileMap {
             }));
         }
 
+        if !rsx_errors.is_empty() {
+            self.rsx_errors.insert(file_path.to_path_buf(), rsx_errors);
+        }
+
         Ok(out_templates)
     }
 
@@ -220,6 +281,97 @@ impl FileMap {
     }
 }
 
+/// Find the files a source file pulls in with `include_str!`, `include_bytes!`, or the `asset!` macro.
+///
+/// This scans the raw tokens rather than the syntax tree so macros nested in other macros (like an
+/// `include_str!` inside `rsx!`) are found too. `include_*!` paths are relative to the source file, while asset
+/// paths are relative to the crate. Remote assets are skipped.
+pub fn find_includes(src: &str, file_path: &Path, crate_dir: &Path) -> Vec<IncludedFile> {
+    let mut includes = vec![];
+    if let Ok(tokens) = src.parse::<proc_macro2::TokenStream>() {
+        find_includes_in_tokens(tokens, file_path, crate_dir, &mut includes);
+    }
+    includes
+}
+
+fn find_includes_in_tokens(
+    tokens: proc_macro2::TokenStream,
+    file_path: &Path,
+    crate_dir: &Path,
+    includes: &mut Vec<IncludedFile>,
+) {
+    use proc_macro2::TokenTree;
+
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    for (idx, token) in tokens.iter().enumerate() {
+        let TokenTree::Ident(ident) = token else {
+            if let TokenTree::Group(group) = token {
+                find_includes_in_tokens(group.stream(), file_path, crate_dir, includes);
+            }
+            continue;
+        };
+
+        let (Some(TokenTree::Punct(bang)), Some(TokenTree::Group(args))) =
+            (tokens.get(idx + 1), tokens.get(idx + 2))
+        else {
+            continue;
+        };
+        if bang.as_char() != '!' {
+            continue;
+        }
+
+        let embedded = match ident.to_string().as_str() {
+            "include_str" | "include_bytes" => true,
+            "asset" => false,
+            _ => continue,
+        };
+        let Some(literal) = first_str_literal(args.stream()) else {
+            continue;
+        };
+
+        let path = match embedded {
+            true => file_path.parent().unwrap_or(crate_dir).join(&literal),
+            false if literal.contains("://") => continue,
+            false => crate_dir.join(literal.trim_start_matches('/')),
+        };
+        includes.push(IncludedFile {
+            path: normalize_path(&path),
+            embedded,
+        });
+    }
+}
+
+/// Resolve the `.` and `..` components of a path without touching the filesystem, so it can be compared
+/// against the paths the watcher reports
+fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Find the first string literal in a macro's arguments, looking inside nested groups like `file("...")`
+fn first_str_literal(tokens: proc_macro2::TokenStream) -> Option<String> {
+    use proc_macro2::TokenTree;
+
+    tokens.into_iter().find_map(|token| match token {
+        TokenTree::Literal(literal) => syn::parse_str::<syn::LitStr>(&literal.to_string())
+            .ok()
+            .map(|literal| literal.value()),
+        TokenTree::Group(group) => first_str_literal(group.stream()),
+        _ => None,
+    })
+}
+
 pub fn template_location(old_start: proc_macro2::LineColumn, file: &Path) -> String {
     let line = old_start.line;
     let column = old_start.column + 1;
@@ -293,7 +445,7 @@ fn find_rs_files(root: PathBuf, filter: &mut impl FnMut(&Path) -> bool) -> FileM
 #[derive(Debug)]
 pub enum HotreloadError {
     Failure(io::Error),
-    Parse,
+    Parse(syn::Error),
     Notreloadable,
 }
 
@@ -301,7 +453,7 @@ impl std::fmt::Display for HotreloadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Failure(err) => write!(f, "Failed to parse file: {}", err),
-            Self::Parse => write!(f, "Failed to parse file"),
+            Self::Parse(err) => write!(f, "Failed to parse file: {}", err),
             Self::Notreloadable => write!(f, "Template is not hotreloadable"),
         }
     }
@@ -312,3 +464,75 @@ impl From<io::Error> for HotreloadError {
         HotreloadError::Failure(err)
     }
 }
+
+#[test]
+fn test_find_includes() {
+    let crate_dir = Path::new("/project");
+    let file = crate_dir.join("src/components/canvas.rs");
+    let src = r#"
+        const SHADER: &str = include_str!("./shader.wgsl");
+        const LOGO: &[u8] = include_bytes!("../../shared/logo.png");
+        static STYLE: Asset = asset!("./assets/canvas.css");
+        static FONT: Asset = asset!(file("/assets/inter.ttf"));
+        static REMOTE: Asset = asset!("https://example.com/pure.css");
+
+        fn app() -> Element {
+            rsx! {
+                style { {include_str!("canvas.css")} }
+            }
+        }
+    "#;
+
+    assert_eq!(
+        find_includes(src, &file, crate_dir),
+        vec![
+            IncludedFile {
+                path: crate_dir.join("src/components/shader.wgsl"),
+                embedded: true,
+            },
+            // Files outside of `src` are tracked too
+            IncludedFile {
+                path: crate_dir.join("shared/logo.png"),
+                embedded: true,
+            },
+            IncludedFile {
+                path: crate_dir.join("assets/canvas.css"),
+                embedded: false,
+            },
+            IncludedFile {
+                path: crate_dir.join("assets/inter.ttf"),
+                embedded: false,
+            },
+            IncludedFile {
+                path: crate_dir.join("src/components/canvas.css"),
+                embedded: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_merge_keeps_member_includes() {
+    use dioxus_html::HtmlCtx;
+
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app");
+    let ui = dir.path().join("ui");
+    fs::create_dir_all(app.join("src")).unwrap();
+    fs::create_dir_all(ui.join("src")).unwrap();
+    fs::write(app.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(
+        ui.join("src/lib.rs"),
+        r#"const STYLE: &str = include_str!("style.css");"#,
+    )
+    .unwrap();
+
+    let mut file_map = FileMap::create_with_filter::<HtmlCtx>(app, |_| false).unwrap();
+    let member_map = FileMap::create_with_filter::<HtmlCtx>(ui.clone(), |_| false).unwrap();
+    file_map.merge(member_map);
+
+    // Editing a file that a member crate includes needs to find its way back to the member's source
+    let (source, include) = file_map.includer_of(&ui.join("src/style.css")).unwrap();
+    assert_eq!(source, ui.join("src/lib.rs"));
+    assert!(include.embedded);
+}
@@ -6,6 +6,7 @@ use crate::cli::serve::Serve;
 use crate::dioxus_crate::DioxusCrate;
 use crate::tracer::CLILogControl;
 use crate::Result;
+use dioxus_hot_reload::ClientMsg;
 use futures_util::FutureExt;
 use tokio::task::yield_now;
 
@@ -59,12 +60,16 @@ pub async fn serve_all(
     // Start the first build
     builder.build()?;
 
-    let mut server = Server::start(&serve, &dioxus_crate);
-    let mut watcher = Watcher::start(&serve, &dioxus_crate);
+    let mut server = Server::start(&serve, &dioxus_crate)?;
+    let mut watcher = Watcher::start(&serve, &dioxus_crate)?;
     let mut screen = Output::start(&serve, log_control).expect("Failed to open terminal logger");
 
     let is_hot_reload = serve.server_arguments.hot_reload.unwrap_or(true);
 
+    // Without a tty we never see ctrl-c as a key event, so listen for the signals directly
+    let shutdown_signal = shutdown_signal();
+    tokio::pin!(shutdown_signal);
+
     loop {
         // Make sure we don't hog the CPU: these loop { select! {} } blocks can starve the executor
         yield_now().await;
@@ -72,10 +77,13 @@ pub async fn serve_all(
         // Draw the state of the server to the screen
         screen.render(&serve, &dioxus_crate, &builder, &server, &watcher);
 
+        // While hot reloading is paused, file events pile up in the watcher and get coalesced on resume
+        let hot_reload_paused = screen.hot_reload_paused;
+
         // And then wait for any updates before redrawing
         tokio::select! {
             // rebuild the project or hotreload it
-            _ = watcher.wait(), if is_hot_reload => {
+            _ = watcher.wait(), if is_hot_reload && !hot_reload_paused => {
                 if !watcher.pending_changes() {
                     continue
                 }
@@ -84,9 +92,16 @@ pub async fn serve_all(
 
                 // if change is hotreloadable, hotreload it
                 // and then send that update to all connected clients
-                if let Some(hr) = watcher.attempt_hot_reload(&dioxus_crate, changed_files) {
+                let hot_reload = watcher.attempt_hot_reload(&dioxus_crate, changed_files);
+
+                // Let clients know about any rsx that failed to parse (or was fixed) so they can show an overlay
+                for (file, errors) in watcher.take_diagnostics() {
+                    server.send_diagnostics(file, errors).await;
+                }
+
+                if let Some(hr) = hot_reload {
                     // Only send a hotreload message for templates and assets - otherwise we'll just get a full rebuild
-                    if hr.templates.is_empty() && hr.assets.is_empty() {
+                    if hr.templates.is_empty() && hr.assets.is_empty() && hr.cleared_templates.is_empty() {
                         continue
                     }
 
@@ -115,6 +130,16 @@ pub async fn serve_all(
                         }
                     }
                     Some(ServerUpdate::Message(msg)) => {
+                        // In-app devtools can ask us to check every file again, in case we missed some changes,
+                        // or to pause hot reloading during a big refactor
+                        if let axum::extract::ws::Message::Text(text) = &msg {
+                            match serde_json::from_str(text) {
+                                Ok(ClientMsg::RequestRescan) => watcher.rescan(),
+                                Ok(ClientMsg::Pause) => screen.set_hot_reload_paused(true),
+                                Ok(ClientMsg::Resume) => screen.set_hot_reload_paused(false),
+                                _ => {}
+                            }
+                        }
                         screen.new_ws_message(TargetPlatform::Web, msg);
                     }
                     None => {}
@@ -203,6 +228,9 @@ pub async fn serve_all(
                     Err(_) => break,
                 }
             }
+
+            // SIGINT/SIGTERM (or a console ctrl event on windows) - shut down cleanly so clients are told
+            _ = &mut shutdown_signal => break,
         }
     }
 
@@ -215,6 +243,29 @@ pub async fn serve_all(
     Ok(())
 }
 
+/// Resolve once the process is asked to exit, either by ctrl-c or, on unix, by SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+            _ = tokio::signal::ctrl_c().await;
+            return;
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        _ = tokio::signal::ctrl_c().await;
+    }
+}
+
 // Grab the output of a future that returns an option or wait forever
 pub(crate) fn next_or_pending<F, T>(f: F) -> impl Future<Output = T>
 where
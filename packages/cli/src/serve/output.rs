@@ -102,6 +102,9 @@ pub struct Output {
     fly_modal_open: bool,
     anim_start: Instant,
 
+    /// Whether the user paused hot reloading. File changes queue up until it's resumed.
+    pub(crate) hot_reload_paused: bool,
+
     tab: Tab,
 
     addr: AddressArguments,
@@ -182,6 +185,7 @@ impl Output {
             term_height: 0,
             num_lines_with_wrapping: 0,
             anim_start: Instant::now(),
+            hot_reload_paused: false,
             tab: Tab::BuildLog,
             addr: cfg.server_arguments.address.clone(),
         })
@@ -395,6 +399,10 @@ impl Output {
                 // todo: reload the app
                 return Ok(true);
             }
+            Event::Key(key) if key.code == KeyCode::Char('p') => {
+                // Pause or resume hot reloading. Changes made while paused are processed in one batch on resume.
+                self.set_hot_reload_paused(!self.hot_reload_paused);
+            }
             Event::Key(key) if key.code == KeyCode::Char('o') => {
                 // Open the running app.
                 open::that(format!("http://{}:{}", self.addr.addr, self.addr.port))?;
@@ -458,6 +466,8 @@ impl Output {
                         },
                     );
                 }
+                // The serve loop handles rescans and pausing, since they go through the watcher
+                Ok(ClientMsg::RequestRescan | ClientMsg::Pause | ClientMsg::Resume) => {}
                 Err(err) => {
                     self.push_log(
                         platform,
@@ -482,6 +492,19 @@ impl Output {
         // prev_scrol == self.scroll
     }
 
+    /// Pause or resume hot reloading, from the keyboard or from a client
+    pub fn set_hot_reload_paused(&mut self, paused: bool) {
+        if self.hot_reload_paused == paused {
+            return;
+        }
+
+        self.hot_reload_paused = paused;
+        match paused {
+            true => tracing::info!("Hot reloading paused"),
+            false => tracing::info!("Hot reloading resumed"),
+        }
+    }
+
     pub fn scroll_to_bottom(&mut self) {
         self.scroll = (self.num_lines_with_wrapping).saturating_sub(self.term_height);
     }
@@ -569,7 +592,7 @@ impl Output {
         _config: &DioxusCrate,
         _build_engine: &Builder,
         server: &Server,
-        _watcher: &Watcher,
+        watcher: &Watcher,
     ) {
         // just drain the build logs
         if !self.interactive {
@@ -642,6 +665,35 @@ impl Output {
                     Span::from(" | ").white(),
                 ];
 
+                if self.hot_reload_paused {
+                    spans.push(Span::from("hot reload paused").light_yellow());
+                    spans.push(Span::from(" | ").white());
+                }
+
+                // Paths we couldn't watch won't hot reload, which is easy to miss in the logs
+                let failed_paths = watcher.failed_paths().len();
+                if failed_paths > 0 {
+                    spans.push(
+                        Span::from(match failed_paths {
+                            1 => "1 path not watched".to_string(),
+                            n => format!("{n} paths not watched"),
+                        })
+                        .red(),
+                    );
+                    spans.push(Span::from(" | ").white());
+                }
+
+                // Show how many apps are listening for hot reloads
+                let clients = server.connected_clients();
+                spans.push(
+                    Span::from(match clients {
+                        1 => "1 client".to_string(),
+                        n => format!("{n} clients"),
+                    })
+                    .dark_gray(),
+                );
+                spans.push(Span::from(" | ").white());
+
                 // If there is build progress, display that next to the platform
                 if !self.build_progress.build_logs.is_empty() {
                     if self
@@ -704,6 +756,7 @@ impl Output {
                         Line::from(" [/] more").gray(),
                         Line::from(" [r] rebuild").gray(),
                         Line::from(" [c] clear").gray(),
+                        Line::from(" [p] pause").gray(),
                         Line::from(" [o] open").gray(),
                         Line::from(" [h] hide").gray(),
                     ])
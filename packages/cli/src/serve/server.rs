@@ -1,7 +1,7 @@
 use crate::dioxus_crate::DioxusCrate;
 use crate::serve::{next_or_pending, Serve};
 use crate::{Error, Result};
-use axum::extract::{Request, State};
+use axum::extract::{ConnectInfo, Query, Request, State};
 use axum::middleware::{self, Next};
 use axum::{
     body::Body,
@@ -19,22 +19,27 @@ use axum::{
 };
 use axum_server::tls_rustls::RustlsConfig;
 use dioxus_cli_config::{Platform, WebHttpsConfig};
-use dioxus_hot_reload::{DevserverMsg, HotReloadMsg};
+use dioxus_hot_reload::{
+    DevserverMsg, HotReloadDiagnostic, HotReloadMsg, VersionCheck, PROTOCOL_VERSION,
+    PROTOCOL_VERSION_HEADER, VERSION, VERSION_HEADER,
+};
 use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender};
 use futures_util::stream;
 use futures_util::{stream::FuturesUnordered, StreamExt};
 use hyper::header::ACCEPT;
 use hyper::HeaderMap;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::net::TcpListener;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::RwLock;
+use std::sync::{PoisonError, RwLock};
 use std::{
     convert::Infallible,
     fs, io,
     net::{IpAddr, SocketAddr},
     process::Command,
+    time::{Duration, Instant},
 };
 use tokio::task::JoinHandle;
 use tower::ServiceBuilder;
@@ -49,6 +54,30 @@ pub enum ServerUpdate {
     Message(Message),
 }
 
+/// A hot reload client connected to the devserver
+#[derive(Debug, Clone)]
+pub(crate) struct ClientInfo {
+    /// The address the client connected from
+    pub address: SocketAddr,
+
+    /// When the client connected
+    pub connected_at: Instant,
+
+    /// The version of dioxus the client was built with, if it told us
+    pub version: Option<String>,
+
+    /// Whether the client can parse our messages. Clients that can't stay connected, but aren't sent hot reloads.
+    pub version_check: VersionCheck,
+
+    /// How many messages we've sent the client
+    pub messages_sent: usize,
+}
+
+struct HotReloadClient {
+    socket: WebSocket,
+    info: ClientInfo,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 enum Status {
@@ -74,20 +103,25 @@ impl SharedStatus {
         Self(Arc::new(RwLock::new(status)))
     }
 
+    // The status is replaced wholesale, so a panic while the lock was held can't leave it half-written.
+    // Recover from poisoning instead of taking down the server and the build status middleware with it.
     fn set(&self, status: Status) {
-        *self.0.write().unwrap() = status;
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = status;
     }
 
     fn get(&self) -> Status {
-        self.0.read().unwrap().clone()
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 }
 
 pub(crate) struct Server {
-    pub hot_reload_sockets: Vec<WebSocket>,
+    hot_reload_sockets: Vec<HotReloadClient>,
     pub build_status_sockets: Vec<WebSocket>,
     pub ip: SocketAddr,
-    pub new_hot_reload_sockets: UnboundedReceiver<WebSocket>,
+    pub new_hot_reload_sockets: UnboundedReceiver<(WebSocket, ClientInfo)>,
     pub new_build_status_sockets: UnboundedReceiver<WebSocket>,
     _server_task: JoinHandle<Result<()>>,
     /// We proxy (not hot reloading) fullstack requests to this port
@@ -96,10 +130,13 @@ pub(crate) struct Server {
     build_status: SharedStatus,
     application_name: String,
     platform: String,
+
+    /// The client versions we've already warned about, so reloading the page doesn't repeat the warning
+    warned_versions: HashSet<String>,
 }
 
 impl Server {
-    pub fn start(serve: &Serve, cfg: &DioxusCrate) -> Self {
+    pub fn start(serve: &Serve, cfg: &DioxusCrate) -> Result<Self> {
         let (hot_reload_sockets_tx, hot_reload_sockets_rx) = futures_channel::mpsc::unbounded();
         let (build_status_sockets_tx, build_status_sockets_rx) = futures_channel::mpsc::unbounded();
 
@@ -109,6 +146,9 @@ impl Server {
         });
 
         let addr = serve.server_arguments.address.address();
+
+        // Bind the devserver up front so a taken port is reported instead of leaving clients with nothing to connect to
+        let listener = bind_devserver(addr)?;
         let start_browser = serve.server_arguments.open.unwrap_or_default();
 
         // If we're serving a fullstack app, we need to find a port to proxy to
@@ -151,14 +191,13 @@ impl Server {
 
             // Start the server with or without rustls
             if let Some(rustls) = rustls {
-                axum_server::bind_rustls(addr, rustls)
-                    .serve(router.into_make_service())
+                axum_server::tls_rustls::from_tcp_rustls(listener, rustls)
+                    .serve(router.into_make_service_with_connect_info::<SocketAddr>())
                     .await?
             } else {
-                // Create a TCP listener bound to the address
                 axum::serve(
-                    tokio::net::TcpListener::bind(&addr).await?,
-                    router.into_make_service(),
+                    tokio::net::TcpListener::from_std(listener)?,
+                    router.into_make_service_with_connect_info::<SocketAddr>(),
                 )
                 .await?
             }
@@ -166,7 +205,7 @@ impl Server {
             Ok(())
         });
 
-        Self {
+        Ok(Self {
             hot_reload_sockets: Default::default(),
             build_status_sockets: Default::default(),
             new_hot_reload_sockets: hot_reload_sockets_rx,
@@ -178,23 +217,14 @@ impl Server {
             build_status,
             application_name: cfg.dioxus_config.application.name.clone(),
             platform: serve.build_arguments.platform().to_string(),
-        }
+            warned_versions: Default::default(),
+        })
     }
 
     /// Sends the current build status to all clients.
     async fn send_build_status(&mut self) {
-        let mut i = 0;
-        while i < self.build_status_sockets.len() {
-            let socket = &mut self.build_status_sockets[i];
-            if send_build_status_to(&self.build_status, socket)
-                .await
-                .is_err()
-            {
-                self.build_status_sockets.remove(i);
-            } else {
-                i += 1;
-            }
-        }
+        let msg = serde_json::to_string(&self.build_status.get()).unwrap();
+        broadcast(&mut self.build_status_sockets, msg).await;
     }
 
     /// Sends a start build message to all clients.
@@ -220,19 +250,13 @@ impl Server {
 
     /// Sends hot reloadable changes to all clients.
     pub async fn send_hotreload(&mut self, reload: HotReloadMsg) {
-        let msg = DevserverMsg::HotReload(reload);
-        let msg = serde_json::to_string(&msg).unwrap();
+        self.send_devserver_message(DevserverMsg::HotReload(reload))
+            .await;
+    }
 
-        // Send the changes to any connected clients
-        let mut i = 0;
-        while i < self.hot_reload_sockets.len() {
-            let socket = &mut self.hot_reload_sockets[i];
-            if socket.send(Message::Text(msg.clone())).await.is_err() {
-                self.hot_reload_sockets.remove(i);
-            } else {
-                i += 1;
-            }
-        }
+    /// The number of clients currently connected to the hot reload socket
+    pub fn connected_clients(&self) -> usize {
+        self.hot_reload_sockets.len()
     }
 
     /// Wait for new clients to be connected and then save them
@@ -243,15 +267,21 @@ impl Server {
             .hot_reload_sockets
             .iter_mut()
             .enumerate()
-            .map(|(idx, socket)| async move { (idx, socket.next().await) })
+            .map(|(idx, client)| async move { (idx, client.socket.next().await) })
             .collect::<FuturesUnordered<_>>();
         let next_new_message = next_or_pending(new_message.next());
 
         tokio::select! {
             new_hot_reload_socket = &mut new_hot_reload_socket => {
-                if let Some(new_socket) = new_hot_reload_socket {
+                if let Some((socket, info)) = new_hot_reload_socket {
                     drop(new_message);
-                    self.hot_reload_sockets.push(new_socket);
+                    tracing::debug!(
+                        "Hot reload client connected from {} (dioxus {})",
+                        info.address,
+                        info.version.as_deref().unwrap_or("unknown")
+                    );
+                    self.warn_on_version_mismatch(&info.version_check);
+                    self.hot_reload_sockets.push(HotReloadClient { socket, info });
                     return Some(ServerUpdate::NewConnection);
                 } else {
                     panic!("Could not receive a socket - the devtools could not boot - the port is likely already in use");
@@ -277,7 +307,13 @@ impl Server {
                     Some(Ok(message)) => return Some(ServerUpdate::Message(message)),
                     _ => {
                         drop(new_message);
-                        _ = self.hot_reload_sockets.remove(idx);
+                        let client = self.hot_reload_sockets.remove(idx).info;
+                        tracing::debug!(
+                            "Hot reload client from {} disconnected after {}s and {} messages",
+                            client.address,
+                            client.connected_at.elapsed().as_secs(),
+                            client.messages_sent
+                        );
                     }
                 }
             }
@@ -315,6 +351,12 @@ impl Server {
             .await;
     }
 
+    /// Sends the parse errors in a file to all clients. An empty list tells clients the file was fixed.
+    pub async fn send_diagnostics(&mut self, file: PathBuf, errors: Vec<HotReloadDiagnostic>) {
+        self.send_devserver_message(DevserverMsg::Diagnostics { file, errors })
+            .await;
+    }
+
     /// Send a shutdown message to all connected clients.
     pub async fn send_shutdown(&mut self) {
         self.send_devserver_message(DevserverMsg::Shutdown).await;
@@ -322,17 +364,49 @@ impl Server {
 
     /// Sends a devserver message to all connected clients.
     async fn send_devserver_message(&mut self, msg: DevserverMsg) {
-        for socket in self.hot_reload_sockets.iter_mut() {
-            _ = socket
-                .send(Message::Text(serde_json::to_string(&msg).unwrap()))
-                .await;
+        let msg = serde_json::to_string(&msg).unwrap();
+        let sockets = self
+            .hot_reload_sockets
+            .iter_mut()
+            .filter(|client| client.info.version_check.is_compatible())
+            .map(|client| &mut client.socket);
+        let mut delivered = send_to_all(sockets, msg).await.into_iter();
+        self.hot_reload_sockets.retain_mut(|client| {
+            if !client.info.version_check.is_compatible() {
+                return true;
+            }
+            let received = delivered.next().unwrap_or(false);
+            client.info.messages_sent += received as usize;
+            received
+        });
+    }
+
+    /// Warn the user if an app was built with a different version of dioxus than this CLI, once per version
+    fn warn_on_version_mismatch(&mut self, check: &VersionCheck) {
+        let client = match check {
+            VersionCheck::Compatible => return,
+            VersionCheck::VersionMismatch { peer } | VersionCheck::ProtocolMismatch { peer } => {
+                peer
+            }
+        };
+        if !self.warned_versions.insert(client.clone()) {
+            return;
+        }
+
+        match check {
+            VersionCheck::ProtocolMismatch { .. } => tracing::warn!(
+                "Hot reloading is disabled for an app built with dioxus {client}: dx {VERSION} speaks a different hot reload protocol. Install a matching version of the CLI to hot reload it"
+            ),
+            _ => tracing::warn!(
+                "The app was built with dioxus {client}, but dx is {VERSION}. Hot reloading should still work, but consider installing a matching version of the CLI"
+            ),
         }
     }
 
     pub async fn shutdown(&mut self) {
         self.send_shutdown().await;
-        for socket in self.hot_reload_sockets.drain(..) {
-            _ = socket.close().await;
+        for client in self.hot_reload_sockets.drain(..) {
+            _ = client.socket.close().await;
         }
     }
 
@@ -353,7 +427,7 @@ impl Server {
 fn setup_router(
     serve: &Serve,
     config: &DioxusCrate,
-    hot_reload_sockets: UnboundedSender<WebSocket>,
+    hot_reload_sockets: UnboundedSender<(WebSocket, ClientInfo)>,
     build_status_sockets: UnboundedSender<WebSocket>,
     fullstack_address: Option<SocketAddr>,
     build_status: SharedStatus,
@@ -418,8 +492,30 @@ fn setup_router(
             .route(
                 "/",
                 get(
-                    |ws: WebSocketUpgrade, ext: Extension<UnboundedSender<WebSocket>>| async move {
-                        ws.on_upgrade(move |socket| async move { _ = ext.0.unbounded_send(socket) })
+                    |ws: WebSocketUpgrade,
+                     ConnectInfo(address): ConnectInfo<SocketAddr>,
+                     Query(client): Query<ClientVersion>,
+                     ext: Extension<UnboundedSender<(WebSocket, ClientInfo)>>| async move {
+                        let info = ClientInfo {
+                            address,
+                            connected_at: Instant::now(),
+                            version_check: VersionCheck::new(
+                                client.protocol.as_deref(),
+                                client.version.as_deref(),
+                            ),
+                            version: client.version,
+                            messages_sent: 0,
+                        };
+
+                        // Advertise our version so clients can tell if they're able to parse our messages
+                        let mut response = ws.on_upgrade(move |socket| async move {
+                            _ = ext.0.unbounded_send((socket, info))
+                        });
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(PROTOCOL_VERSION_HEADER, HeaderValue::from(PROTOCOL_VERSION));
+                        headers.insert(VERSION_HEADER, HeaderValue::from_static(VERSION));
+                        response
                     },
                 ),
             )
@@ -511,6 +607,13 @@ fn no_cache(
     response
 }
 
+/// The versions a hot reload client reports in the query string when it connects
+#[derive(Deserialize, Default)]
+struct ClientVersion {
+    protocol: Option<String>,
+    version: Option<String>,
+}
+
 pub fn insert_no_cache_headers(headers: &mut HeaderMap) {
     headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
     headers.insert(PRAGMA, HeaderValue::from_static("no-cache"));
@@ -602,6 +705,29 @@ pub(crate) fn open_browser(base_path: Option<String>, address: SocketAddr, https
     _ = open::that(format!("{protocol}://{address}{base_path}"));
 }
 
+/// Bind the devserver's TCP listener, explaining the common reasons it can fail
+fn bind_devserver(addr: SocketAddr) -> Result<TcpListener> {
+    let listener = TcpListener::bind(addr).map_err(|err| {
+        let hint = match err.kind() {
+            io::ErrorKind::AddrInUse => {
+                " Is another `dx serve` running? Pick a different port with `--port`."
+            }
+            io::ErrorKind::PermissionDenied => {
+                " Ports below 1024 usually require elevated privileges."
+            }
+            _ => "",
+        };
+        Error::Unique(format!(
+            "Failed to start the devserver on {addr}: {err}.{hint}"
+        ))
+    })?;
+
+    // tokio and axum-server both expect a nonblocking listener
+    listener.set_nonblocking(true)?;
+
+    Ok(listener)
+}
+
 fn get_available_port(address: IpAddr) -> Option<u16> {
     TcpListener::bind((address, 0))
         .map(|listener| listener.local_addr().unwrap().port())
@@ -639,6 +765,35 @@ async fn build_status_middleware(
     next.run(request).await
 }
 
+/// How long we wait for a single client to accept a message before treating it as dead
+const SEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Send a message to every socket concurrently, dropping any socket that errors or doesn't accept the
+/// message within [`SEND_TIMEOUT`].
+///
+/// This way a single stalled client (e.g. one paused in a debugger) can't hold up delivery to the others.
+async fn broadcast(sockets: &mut Vec<WebSocket>, msg: String) {
+    let mut delivered = send_to_all(sockets.iter_mut(), msg).await.into_iter();
+    sockets.retain(|_| delivered.next().unwrap_or(false));
+}
+
+/// Send a message to every socket at once, returning whether each socket received it in time
+async fn send_to_all<'a>(
+    sockets: impl Iterator<Item = &'a mut WebSocket>,
+    msg: String,
+) -> Vec<bool> {
+    futures_util::future::join_all(sockets.map(|socket| {
+        let msg = Message::Text(msg.clone());
+        async move {
+            matches!(
+                tokio::time::timeout(SEND_TIMEOUT, socket.send(msg)).await,
+                Ok(Ok(()))
+            )
+        }
+    }))
+    .await
+}
+
 async fn send_build_status_to(
     build_status: &SharedStatus,
     socket: &mut WebSocket,
@@ -646,3 +801,36 @@ async fn send_build_status_to(
     let msg = serde_json::to_string(&build_status.get()).unwrap();
     socket.send(Message::Text(msg)).await
 }
+
+#[test]
+fn test_bind_devserver_reports_port_in_use() {
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = taken.local_addr().unwrap();
+
+    let err = bind_devserver(addr).unwrap_err();
+    assert!(err.to_string().contains("Is another `dx serve` running?"));
+}
+
+#[test]
+fn test_shared_status_survives_poisoning() {
+    let status = SharedStatus::new(Status::Ready);
+
+    let poisoned = status.clone();
+    _ = std::thread::spawn(move || {
+        let _guard = poisoned.0.write().unwrap();
+        panic!("poison the status lock");
+    })
+    .join();
+    assert!(status.0.is_poisoned());
+
+    assert_eq!(status.get(), Status::Ready);
+    status.set(Status::BuildError {
+        error: "oops".to_string(),
+    });
+    assert_eq!(
+        status.get(),
+        Status::BuildError {
+            error: "oops".to_string()
+        }
+    );
+}
@@ -1,16 +1,24 @@
-use std::collections::{HashMap, HashSet};
-use std::{fs, path::PathBuf, time::Duration};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use super::hot_reloading_file_map::HotreloadError;
 use crate::serve::hot_reloading_file_map::FileMap;
-use crate::{cli::serve::Serve, dioxus_crate::DioxusCrate};
-use dioxus_hot_reload::HotReloadMsg;
+use crate::{cli::serve::Serve, dioxus_crate::DioxusCrate, Result};
+use dioxus_cli_config::{ExtensionPolicy, WebWatcherConfig};
+use dioxus_core::internal::HotReloadTemplateWithLocation;
+use dioxus_hot_reload::{DiagnosticCategory, HotReloadAsset, HotReloadDiagnostic, HotReloadMsg};
 use dioxus_html::HtmlCtx;
 use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender};
 use futures_util::StreamExt;
-use ignore::gitignore::Gitignore;
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::Match;
 use notify::{
-    event::{MetadataKind, ModifyKind},
+    event::{CreateKind, MetadataKind, ModifyKind},
     Config, EventKind,
 };
 
@@ -21,158 +29,299 @@ use notify::{
 pub struct Watcher {
     _tx: UnboundedSender<notify::Event>,
     rx: UnboundedReceiver<notify::Event>,
-    _last_update_time: i64,
-    _watcher: Box<dyn notify::Watcher>,
+    debounce: Duration,
+    storm_threshold: usize,
+    storm_debounce: Duration,
+    max_debounce: Duration,
+    max_settle: Duration,
+    watchers: FileWatchers,
     queued_events: Vec<notify::Event>,
     file_map: FileMap,
-    ignore: Gitignore,
+    ignore: IgnoreRules,
+    reported_ignored: HashSet<PathBuf>,
+    watch_filter: Option<Override>,
+    workspace_dirs: Vec<PathBuf>,
+    lockfiles: Vec<PathBuf>,
+    target_dir: PathBuf,
+    asset_dirs: Vec<PathBuf>,
+    max_asset_hash_size: u64,
+    default_editor_ignores: bool,
+    path_aliases: PathAliases,
+    manifest: Option<toml::Table>,
+    content_hashes: ContentHashes,
     applied_hot_reload_message: Option<HotReloadMsg>,
+    /// A hash of the last version of each template we sent to clients, by template name
+    sent_templates: HashMap<String, u64>,
+    files_with_errors: HashSet<PathBuf>,
+    /// Whether the files being hot reloaded came from a rescan
+    resyncing: bool,
+    pending_diagnostics: Vec<(PathBuf, Vec<HotReloadDiagnostic>)>,
+    /// The included files we registered with the watcher ourselves, since they weren't under a watched path
+    watched_includes: HashSet<PathBuf>,
+    failed_paths: Vec<(PathBuf, String)>,
 }
 
 impl Watcher {
-    pub fn start(serve: &Serve, config: &DioxusCrate) -> Self {
+    pub fn start(serve: &Serve, config: &DioxusCrate) -> Result<Self> {
         let (tx, rx) = futures_channel::mpsc::unbounded();
 
         // Extend the watch path to include:
         // - the assets directory - this is so we can hotreload CSS and other assets by default
         // - the Cargo.toml file - this is so we can hotreload the project if the user changes dependencies
         // - the Dioxus.toml file - this is so we can hotreload the project if the user changes the Dioxus config
+        // - the .gitignore and .dioxusignore files - this is so edits to the ignore rules take effect without restarting.
+        //   They're watched even if they don't exist yet, so creating one mid-session is picked up too
+        let crate_dir = config.crate_dir();
         let mut allow_watch_path = config.dioxus_config.web.watcher.watch_path.clone();
         allow_watch_path.push(config.dioxus_config.application.asset_dir.clone());
+        allow_watch_path.extend(config.dioxus_config.web.watcher.asset_dirs.iter().cloned());
         allow_watch_path.push("Cargo.toml".to_string().into());
         allow_watch_path.push("Dioxus.toml".to_string().into());
+        allow_watch_path.extend(IGNORE_FILES.iter().map(PathBuf::from));
         allow_watch_path.dedup();
 
-        let crate_dir = config.crate_dir();
-        let mut builder = ignore::gitignore::GitignoreBuilder::new(&crate_dir);
-        builder.add(crate_dir.join(".gitignore"));
-
-        let out_dir = config.out_dir();
-        let out_dir_str = out_dir.display().to_string();
-
-        let excluded_paths = vec![
-            ".git",
-            ".github",
-            ".vscode",
-            "target",
-            "node_modules",
-            "dist",
-            &out_dir_str,
-        ];
-        for path in excluded_paths {
-            builder
-                .add_line(None, path)
-                .expect("failed to add path to file excluder");
-        }
-        let ignore = builder.build().unwrap();
+        let target_dir = config.target_dir();
+        let ignore = IgnoreRules::new(
+            &crate_dir,
+            &config.out_dir(),
+            &target_dir,
+            &config.dioxus_config.web.watcher,
+        );
 
-        // Build the event handler for notify.
-        let notify_event_handler = {
-            let tx = tx.clone();
-            move |info: notify::Result<notify::Event>| {
-                if let Ok(e) = info {
-                    if is_allowed_notify_event(&e) {
-                        _ = tx.unbounded_send(e);
-                    }
-                }
-            }
-        };
+        // Glob patterns can't be handed to notify directly, so we watch the directory they're rooted
+        // in and filter the events against the patterns themselves
+        let watch_filter = allow_watch_path
+            .iter()
+            .any(|path| is_glob(path))
+            .then(|| build_watch_filter(&crate_dir, &allow_watch_path));
+        let mut allow_watch_path: Vec<PathBuf> = allow_watch_path
+            .iter()
+            .map(|path| glob_root(path))
+            .collect();
+        allow_watch_path.dedup();
 
         // If we are in WSL, we must use Notify's poll watcher due to an event propagation issue.
-        let is_wsl = is_wsl();
-        const NOTIFY_ERROR_MSG: &str = "Failed to create file watcher.\nEnsure you have the required permissions to watch the specified directories.";
+        // Some network filesystems and docker bind mounts never deliver native events either, so the
+        // user can also opt into polling explicitly.
+        let poll_interval =
+            Duration::from_secs(serve.server_arguments.wsl_file_poll_interval.unwrap_or(2) as u64);
+        let use_poll_watcher = is_wsl() || serve.server_arguments.poll_watcher.unwrap_or_default();
 
         // Create the file watcher.
-        let mut watcher: Box<dyn notify::Watcher> = match is_wsl {
-            true => {
-                let poll_interval = Duration::from_secs(
-                    serve.server_arguments.wsl_file_poll_interval.unwrap_or(2) as u64,
-                );
-
-                Box::new(
-                    notify::PollWatcher::new(
-                        notify_event_handler,
-                        Config::default().with_poll_interval(poll_interval),
-                    )
-                    .expect(NOTIFY_ERROR_MSG),
-                )
-            }
-            false => {
-                Box::new(notify::recommended_watcher(notify_event_handler).expect(NOTIFY_ERROR_MSG))
-            }
+        let mut watchers = FileWatchers {
+            native: create_watcher(
+                notify_event_handler(tx.clone()),
+                use_poll_watcher,
+                poll_interval,
+            )?,
+            poll: None,
+            tx: tx.clone(),
+            use_poll_watcher,
+            poll_interval,
+            roots: vec![],
+            missing: vec![],
+            last_recreated: None,
         };
 
         // Watch the specified paths
         // todo: make sure we don't double-watch paths if they're nested
-        for sub_path in allow_watch_path {
-            let path = &config.crate_dir().join(sub_path);
+        // If the path is ignored, don't watch it
+        let mut watch_paths: Vec<PathBuf> = allow_watch_path
+            .into_iter()
+            .map(|sub_path| crate_dir.join(sub_path))
+            .filter(|path| !ignore.matched(path, path.is_dir()).is_ignore())
+            .collect();
 
-            // If the path is ignored, don't watch it
-            if ignore.matched(path, path.is_dir()).is_ignore() {
-                continue;
-            }
+        // Workspace crates we depend on get their sources watched too, so rsx in component libraries
+        // next to the app can hot reload
+        let workspace_dirs = match config.dioxus_config.web.watcher.watch_workspace {
+            true => config.workspace_dependency_dirs(),
+            false => vec![],
+        };
+        watch_paths.extend(workspace_dirs.iter().map(|dir| dir.join("src")));
 
-            let mode = notify::RecursiveMode::Recursive;
+        let failed_paths = register_watch_paths(&mut watchers, &watch_paths)?;
 
-            if let Err(err) = watcher.watch(path, mode) {
-                tracing::warn!("Failed to watch path: {}", err);
+        // The lockfile lives in the workspace root, which we don't watch otherwise. Cargo only reads the workspace's
+        // lockfile, so a member crate never has one of its own worth watching
+        let lockfiles = match config.dioxus_config.web.watcher.rebuild_on_lockfile_change {
+            true => vec![config.workspace_dir().join("Cargo.lock")],
+            false => vec![],
+        };
+        for lockfile in &lockfiles {
+            match watchers.watch(lockfile) {
+                Ok(()) => {}
+                Err(err) if is_not_found_error(&err) => {}
+                Err(err) => tracing::warn!("Failed to watch {}: {}", lockfile.display(), err),
             }
         }
 
         // Probe the entire project looking for our rsx calls
         // Whenever we get an update from the file watcher, we'll try to hotreload against this file map
-        let file_map = FileMap::create_with_filter::<HtmlCtx>(config.crate_dir(), |path| {
+        let mut file_map = FileMap::create_with_filter::<HtmlCtx>(config.crate_dir(), |path| {
             ignore.matched(path, path.is_dir()).is_ignore()
-        })
-        .unwrap();
+        })?;
+        for dir in &workspace_dirs {
+            let member_map = FileMap::create_with_filter::<HtmlCtx>(dir.clone(), |path| {
+                ignore.matched(path, path.is_dir()).is_ignore()
+            })?;
+            file_map.merge(member_map);
+        }
 
-        Self {
+        let mut watcher = Self {
             _tx: tx,
             rx,
-            _watcher: watcher,
+            watchers,
             file_map,
             ignore,
+            reported_ignored: HashSet::new(),
+            watch_filter,
+            path_aliases: PathAliases::new(std::iter::once(&crate_dir).chain(&workspace_dirs)),
+            workspace_dirs,
+            lockfiles,
+            target_dir,
+            asset_dirs: config
+                .dioxus_config
+                .web
+                .watcher
+                .asset_dirs
+                .iter()
+                .map(|dir| crate_dir.join(dir))
+                .collect(),
+            max_asset_hash_size: config.dioxus_config.web.watcher.max_asset_hash_size,
+            default_editor_ignores: config.dioxus_config.web.watcher.default_editor_ignores,
+            manifest: read_manifest(&crate_dir.join("Cargo.toml")).ok(),
+            content_hashes: ContentHashes::default(),
             queued_events: Vec::new(),
-            _last_update_time: chrono::Local::now().timestamp(),
+            debounce: Duration::from_millis(config.dioxus_config.web.watcher.debounce_ms),
+            storm_threshold: config.dioxus_config.web.watcher.storm_threshold,
+            storm_debounce: Duration::from_millis(
+                config.dioxus_config.web.watcher.storm_debounce_ms,
+            ),
+            max_debounce: Duration::from_millis(config.dioxus_config.web.watcher.max_debounce_ms),
+            max_settle: Duration::from_millis(config.dioxus_config.web.watcher.max_settle_ms),
             applied_hot_reload_message: None,
-        }
+            sent_templates: HashMap::new(),
+            files_with_errors: HashSet::new(),
+            resyncing: false,
+            pending_diagnostics: Vec::new(),
+            watched_includes: HashSet::new(),
+            failed_paths,
+        };
+
+        // Files pulled in with include_str! and friends may live outside the watched paths
+        watcher.watch_includes();
+
+        Ok(watcher)
     }
 
     /// A cancel safe handle to the file watcher
     ///
-    /// todo: this should be simpler logic?
+    /// Once we have at least one event, we keep collecting events until the watcher has been quiet for
+    /// the debounce window. This makes sure a burst of saves is processed as a single batch. Then we wait
+    /// for the rust files in the batch to finish being written.
     pub async fn wait(&mut self) {
         // Pull off any queued events in succession
         while let Ok(Some(event)) = self.rx.try_next() {
-            self.queued_events.push(event);
+            queue_event(&mut self.queued_events, event);
         }
 
-        if !self.queued_events.is_empty() {
-            return;
+        // If there are no queued events, wait for the next event
+        // Paths that don't exist yet can be created without us getting an event for them, so we check on them
+        // every so often while we wait
+        while self.queued_events.is_empty() {
+            tokio::select! {
+                event = self.rx.next() => match event {
+                    Some(event) => queue_event(&mut self.queued_events, event),
+                    None => return,
+                },
+                _ = tokio::time::sleep(MISSING_PATH_RETRY), if self.watchers.has_missing() => {
+                    let events = self.rewatch(&[]);
+                    self.queued_events.extend(events);
+                }
+            }
         }
 
-        // If there are no queued events, wait for the next event
-        if let Some(event) = self.rx.next().await {
-            self.queued_events.push(event);
+        // Don't let a steady stream of events hold the batch back forever
+        let deadline = tokio::time::Instant::now() + self.max_debounce;
+        debounce_events(
+            &mut self.rx,
+            &mut self.queued_events,
+            self.debounce,
+            deadline,
+        )
+        .await;
+
+        // A branch switch can rewrite hundreds of files in bursts spread over a second or two. Once a batch looks
+        // like one of those storms, wait for a longer quiet period so the whole storm is handled as a single
+        // batch, and triggers at most one rebuild.
+        if is_event_storm(&self.queued_events, self.storm_threshold) {
+            tracing::debug!("Waiting for a burst of file changes to settle");
+            debounce_events(
+                &mut self.rx,
+                &mut self.queued_events,
+                self.storm_debounce,
+                deadline,
+            )
+            .await;
+        }
+
+        // Editors may still be writing the files we were told about. Waiting for them here instead of while the
+        // batch is handled keeps the devserver serving clients and builds in the meantime. Every file in the batch
+        // shares one budget
+        let settle_deadline = tokio::time::Instant::now() + self.max_settle;
+        let rust_files: BTreeSet<&PathBuf> = self
+            .queued_events
+            .iter()
+            .flat_map(|event| event.paths.iter())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+            .collect();
+        for path in rust_files {
+            wait_for_settle(path, settle_deadline).await;
         }
     }
 
     /// Deques changed files from the event queue, doing the proper intelligent filtering
     pub fn dequeue_changed_files(&mut self, config: &DioxusCrate) -> Vec<PathBuf> {
-        let mut all_mods: Vec<PathBuf> = vec![];
-
-        // Decompose the events into a list of all the files that have changed
-        for event in self.queued_events.drain(..) {
-            // We only care about certain events.
-            if !is_allowed_notify_event(&event) {
-                continue;
-            }
+        // If notify dropped events (or the watcher itself failed), we can't trust the events we have to describe
+        // everything that changed, so we check every file we track after handling them
+        let needs_resync = self.queued_events.iter().any(|event| event.need_rescan());
+        if self
+            .queued_events
+            .iter()
+            .any(|event| event.info() == Some(WATCHER_ERROR))
+        {
+            self.watchers.recreate();
+        }
 
-            for path in event.paths {
-                all_mods.push(path.clone());
-            }
+        // Notify drops the watch on a directory when it's removed, so watch roots that were removed or renamed
+        // need to be registered again. Creating a directory may also create a root we couldn't watch yet.
+        let touched: Vec<PathBuf> = self
+            .queued_events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event.kind,
+                    EventKind::Create(_)
+                        | EventKind::Remove(_)
+                        | EventKind::Modify(ModifyKind::Name(_))
+                )
+            })
+            .flat_map(|event| event.paths.iter())
+            .map(|path| self.path_aliases.alias(path.clone()))
+            .collect();
+        if !touched.is_empty() {
+            let events = self.rewatch(&touched);
+            self.queued_events.extend(events);
         }
 
+        // Decompose the events into a list of all the files that have changed
+        // Notify may report paths through a different name than cargo gave us for the crate (e.g. `/private/tmp`
+        // instead of `/tmp` on macOS), so map them back before matching them against anything
+        let all_mods = self
+            .path_aliases
+            .normalize(changed_paths(self.queued_events.drain(..)));
+
         let mut modified_files = vec![];
 
         // For the non-rust files, we want to check if it's an asset file
@@ -185,8 +334,72 @@ impl Watcher {
             .canonicalize()
             .ok();
 
+        // If an ignore file was created or edited, rebuild the matcher in place before filtering the rest of
+        // the batch, so the new rules already apply to it
+        let crate_dir = config.crate_dir();
+        if let Some(path) = all_mods
+            .iter()
+            .find(|path| is_ignore_file(path, &crate_dir))
+        {
+            tracing::info!("Reloading ignore rules from {}", path.display());
+            self.ignore = IgnoreRules::new(
+                &crate_dir,
+                &config.out_dir(),
+                &self.target_dir,
+                &config.dioxus_config.web.watcher,
+            );
+            self.reported_ignored.clear();
+        }
+
         for path in all_mods.iter() {
-            if path.extension().is_none() {
+            // Changes to the ignore rules are not changes we need to hot reload or rebuild for
+            if is_ignore_file(path, &crate_dir) {
+                continue;
+            }
+
+            // The lockfiles are watched explicitly, so they skip the ignore rules and watch path filters. We
+            // still skip no-op rewrites, which cargo does a lot
+            if self.lockfiles.contains(path) {
+                if self.content_hashes.changed(path, None) {
+                    modified_files.push(path.clone());
+                }
+                continue;
+            }
+
+            // Same goes for files the source includes with a macro, which can be anywhere and have any extension
+            if self.file_map.includer_of(path).is_some() {
+                if self.content_hashes.changed(path, None) {
+                    modified_files.push(path.clone());
+                }
+                continue;
+            }
+
+            // If the file is an editor backup, swap, or lock file, or a hidden file, ignore it completely (no rebuilds)
+            // This runs before the extension check since some of these (like vim's `4913`) have no extension
+            if is_backup_file(path.to_path_buf(), self.default_editor_ignores) {
+                tracing::trace!("Ignoring backup file: {:?}", path);
+                continue;
+            }
+
+            // Every file in the asset dirs is an asset, whatever its extension
+            let in_asset_dir = self.asset_dirs.iter().any(|dir| path.starts_with(dir));
+
+            // A deleted or moved directory may only show up as a single event for the directory itself, so
+            // expand it into the files we were tracking under it. Those are then handled as deleted files.
+            if !in_asset_dir && path.extension().is_none() && !path.exists() {
+                let removed = self.file_map.files_under(path);
+                modified_files.extend(removed.into_iter().filter(|file| !all_mods.contains(file)));
+                continue;
+            }
+
+            if !in_asset_dir && path.extension().is_none() {
+                continue;
+            }
+
+            // Editors that save by writing a temp file and renaming it over the original report events for
+            // the temp file too. It's gone by the time we get here, so skip it
+            if is_transient_file(path, &self.file_map) {
+                tracing::trace!("Ignoring transient file: {:?}", path);
                 continue;
             }
 
@@ -200,20 +413,67 @@ impl Watcher {
                 }
             }
 
-            // If the extension is a backup file, or a hidden file, ignore it completely (no rebuilds)
-            if is_backup_file(path.to_path_buf()) {
-                tracing::trace!("Ignoring backup file: {:?}", path);
+            // If the path is ignored, don't watch it
+            // We log the rule that excluded it (once per path) to help diagnose files that don't hot reload
+            if let Match::Ignore(glob) = self.ignore.matched(path, path.is_dir()) {
+                if self.reported_ignored.insert(path.clone()) {
+                    let source = glob
+                        .from()
+                        .map(|from| from.display().to_string())
+                        .unwrap_or_else(|| "the default excludes".to_string());
+                    tracing::debug!(
+                        "Ignoring changes to {path:?} because it matches {:?} in {source}",
+                        glob.original()
+                    );
+                }
                 continue;
             }
 
-            // If the path is ignored, don't watch it
-            if self.ignore.matched(path, path.is_dir()).is_ignore() {
+            // If the path doesn't match any of the watched globs, skip it
+            // The globs are relative to the app crate, so they don't apply to other workspace crates
+            if let Some(filter) = &self.watch_filter {
+                let in_workspace_crate = self
+                    .workspace_dirs
+                    .iter()
+                    .any(|dir| strip_path_prefix(path, dir, CASE_INSENSITIVE_FS).is_some());
+                if !in_workspace_crate && filter.matched(path, path.is_dir()).is_ignore() {
+                    continue;
+                }
+            }
+
+            // Skip saves that didn't change the contents, like `touch` or a formatter rewriting identical bytes
+            // Large assets are always treated as changed so we don't have to read them
+            let known_contents = self
+                .file_map
+                .map
+                .get(path)
+                .map(|cached| cached.raw.as_str());
+            let too_large_to_hash = in_asset_dir
+                && fs::metadata(path)
+                    .is_ok_and(|metadata| metadata.len() > self.max_asset_hash_size);
+            if !too_large_to_hash && !self.content_hashes.changed(path, known_contents) {
+                tracing::trace!("Ignoring unchanged file: {:?}", path);
                 continue;
             }
 
             modified_files.push(path.clone());
         }
 
+        if needs_resync {
+            let (scanned, resynced) =
+                changed_since_last_seen(&self.file_map, &mut self.content_hashes);
+            tracing::info!(
+                "Rescanned {scanned} files, {} changed since we last saw them",
+                resynced.len()
+            );
+            self.resyncing = true;
+            for path in resynced {
+                if !modified_files.contains(&path) {
+                    modified_files.push(path);
+                }
+            }
+        }
+
         modified_files
     }
 
@@ -222,31 +482,127 @@ impl Watcher {
         config: &DioxusCrate,
         modified_files: Vec<PathBuf>,
     ) -> Option<HotReloadMsg> {
+        let resyncing = std::mem::take(&mut self.resyncing);
+
         // If we have any changes to the rust files, we need to update the file map
         let crate_dir = config.crate_dir();
         let mut templates = vec![];
+        let mut cleared_templates = vec![];
 
         // Prepare the hotreload message we need to send
-        let mut edited_rust_files = Vec::new();
-        let mut assets = Vec::new();
-        let mut unknown_files = Vec::new();
+        let asset_dir = crate_dir.join(&config.dioxus_config.application.asset_dir);
+        let mut policies = config.dioxus_config.web.watcher.extensions.clone();
+        // Some projects compile their css (e.g. with a build script), so they need a rebuild to see changes
+        if config.dioxus_config.web.watcher.rebuild_on_css_change {
+            policies
+                .entry("css".to_string())
+                .or_insert(ExtensionPolicy::Rebuild);
+        }
 
-        for path in modified_files {
-            // for various assets that might be linked in, we just try to hotreloading them forcefully
-            // That is, unless they appear in an include! macro, in which case we need to a full rebuild....
-            let Some(ext) = path.extension().and_then(|v| v.to_str()) else {
-                continue;
-            };
+        // Files compiled into the app with include_str! or include_bytes! need a rebuild to show up. Assets
+        // referenced with asset! can still be hot reloaded if they're a kind of asset we know how to reload.
+        let changed_include = modified_files.iter().find_map(|path| {
+            let (source, include) = self.file_map.includer_of(path)?;
+            let reloadable_asset = self.asset_dirs.iter().any(|dir| path.starts_with(dir))
+                || path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .and_then(|ext| extension_policy(ext, path, &asset_dir, &policies))
+                    == Some(ExtensionPolicy::Asset);
+            (include.embedded || !reloadable_asset).then_some((path, source))
+        });
+        if let Some((path, source)) = changed_include {
+            tracing::info!(
+                "Rebuilding because {} changed, which is included by {}",
+                path.strip_prefix(&crate_dir).unwrap_or(path).display(),
+                source.strip_prefix(&crate_dir).unwrap_or(source).display()
+            );
+            return None;
+        }
 
-            match ext {
-                "rs" => edited_rust_files.push(path),
-                _ if path.starts_with("assets") => assets.push(path),
-                _ => unknown_files.push(path),
-            }
+        let ChangedFiles {
+            rust: edited_rust_files,
+            assets,
+            rebuild,
+            unknown: unknown_files,
+        } = ChangedFiles::sort(modified_files, &asset_dir, &self.asset_dirs, &policies);
+
+        if !rebuild.is_empty() {
+            return None;
+        }
+
+        if let Some(lockfile) = unknown_files
+            .iter()
+            .find(|path| self.lockfiles.contains(path))
+        {
+            let lockfile = lockfile
+                .strip_prefix(config.workspace_dir())
+                .unwrap_or(lockfile);
+            tracing::info!("Rebuilding because {} changed", lockfile.display());
+            return None;
+        }
+
+        // Cargo.toml edits only need a rebuild if they change something that affects the build
+        let manifest_path = crate_dir.join("Cargo.toml");
+        if unknown_files.contains(&manifest_path)
+            && self.manifest_needs_rebuild(
+                &manifest_path,
+                config
+                    .dioxus_config
+                    .web
+                    .watcher
+                    .rebuild_on_any_manifest_change,
+            )
+        {
+            return None;
         }
 
         for rust_file in edited_rust_files {
-            match self.file_map.update_rsx::<HtmlCtx>(&rust_file, &crate_dir) {
+            let display_path = rust_file
+                .strip_prefix(&crate_dir)
+                .or_else(|_| rust_file.strip_prefix(config.workspace_dir()))
+                .unwrap_or(&rust_file)
+                .to_path_buf();
+
+            // If the file was deleted, stop tracking it and tell clients to forget its hot reloaded templates
+            if !rust_file.exists() {
+                cleared_templates.extend(self.file_map.remove_file(&rust_file));
+                if self.files_with_errors.remove(&rust_file) {
+                    self.pending_diagnostics.push((display_path, vec![]));
+                }
+                continue;
+            }
+
+            let file_crate_dir = owning_crate_dir(&rust_file, &crate_dir, &self.workspace_dirs);
+            let result = self
+                .file_map
+                .update_rsx::<HtmlCtx>(&rust_file, file_crate_dir);
+
+            // Let clients know about new parse and read errors, and clear the errors of files that parse again
+            // Individual rsx blocks can fail to parse while the rest of the file hot reloads
+            match (&result, self.file_map.rsx_errors.get(&rust_file)) {
+                (Err(err @ (HotreloadError::Parse(_) | HotreloadError::Failure(_))), _) => {
+                    let src = fs::read_to_string(&rust_file).unwrap_or_default();
+                    self.files_with_errors.insert(rust_file.clone());
+                    self.pending_diagnostics
+                        .push((display_path, diagnostic_from_error(err, &src)));
+                }
+                (Ok(_), Some(errors)) => {
+                    let src = fs::read_to_string(&rust_file).unwrap_or_default();
+                    let diagnostics = errors
+                        .iter()
+                        .map(|err| diagnostic_from_syn(err, &src))
+                        .collect();
+                    self.files_with_errors.insert(rust_file.clone());
+                    self.pending_diagnostics.push((display_path, diagnostics));
+                }
+                _ if self.files_with_errors.remove(&rust_file) => {
+                    self.pending_diagnostics.push((display_path, vec![]));
+                }
+                _ => {}
+            }
+
+            match result {
                 Ok(hotreloaded_templates) => {
                     templates.extend(hotreloaded_templates);
                 }
@@ -254,8 +610,8 @@ impl Watcher {
                 Err(HotreloadError::Notreloadable) => return None,
                 // The rust file may have failed to parse, but that is most likely
                 // because the user is in the middle of adding new code
-                // We just ignore the error and let Rust analyzer warn about the problem
-                Err(HotreloadError::Parse) => {}
+                // We don't rebuild and let Rust analyzer (and the client's diagnostics overlay) warn about the problem
+                Err(HotreloadError::Parse(_)) => {}
                 // Otherwise just log the error
                 Err(err) => {
                     tracing::error!("Error hotreloading file {rust_file:?}: {err}")
@@ -263,17 +619,145 @@ impl Watcher {
             }
         }
 
+        self.watch_includes();
+
+        let asset_roots: Vec<&Path> = std::iter::once(asset_dir.as_path())
+            .chain(self.asset_dirs.iter().map(|dir| dir.as_path()))
+            .chain(std::iter::once(crate_dir.as_path()))
+            .collect();
+        let asset_details = assets
+            .iter()
+            .map(|asset| asset_details(asset, &asset_roots, self.max_asset_hash_size))
+            .collect();
+
+        // Clients already have the templates that haven't changed since we last sent them
+        for location in &cleared_templates {
+            self.sent_templates.remove(location);
+        }
+        let templates = unsent_templates(&mut self.sent_templates, templates);
+
         let msg = HotReloadMsg {
             templates,
             assets,
             unknown_files,
+            cleared_templates,
+            asset_details,
         };
 
+        if resyncing {
+            tracing::info!("Rescan updated {} templates", msg.templates.len());
+        }
+
         self.add_hot_reload_message(&msg);
 
         Some(msg)
     }
 
+    /// Check every file we track for changes, in case the filesystem changed without us hearing about it (e.g.
+    /// generated code, or a network share that drops events).
+    ///
+    /// This goes through the same path as the rescans notify asks for when it drops events, so the changes show
+    /// up on the next [`Self::wait`] and are hot reloaded as a single batch.
+    pub fn rescan(&mut self) {
+        tracing::info!("Rescanning the project for changes");
+        self.queued_events
+            .push(notify::Event::new(EventKind::Other).set_flag(notify::event::Flag::Rescan));
+    }
+
+    /// Watch the files the source includes with a macro that aren't under a watched path already, and stop
+    /// watching the ones nothing includes anymore
+    fn watch_includes(&mut self) {
+        let included: HashSet<PathBuf> = self
+            .file_map
+            .includes
+            .values()
+            .flatten()
+            .map(|include| include.path.clone())
+            .collect();
+
+        for path in self.watched_includes.iter() {
+            if !included.contains(path) {
+                self.watchers.unwatch(path);
+            }
+        }
+        self.watched_includes.retain(|path| included.contains(path));
+
+        for path in included {
+            if self.watched_includes.contains(&path) || self.watchers.covers(&path) {
+                continue;
+            }
+
+            match self.watchers.watch(&path) {
+                Ok(()) => {}
+                Err(err) if is_not_found_error(&err) => {}
+                Err(err) => {
+                    tracing::warn!("Failed to watch included file {}: {err}", path.display());
+                    continue;
+                }
+            }
+            self.watched_includes.insert(path);
+        }
+    }
+
+    /// Register the watch roots that were removed or didn't exist yet again.
+    ///
+    /// Files may have been written into a root before it was watched, so this returns a create event for every
+    /// file in the roots that are watched again.
+    fn rewatch(&mut self, touched: &[PathBuf]) -> Vec<notify::Event> {
+        let mut events = vec![];
+
+        for root in self.watchers.refresh(touched) {
+            tracing::info!("Watching {}", root.display());
+            events.extend(created_events(&root));
+        }
+
+        events
+    }
+
+    /// Compare the manifest against the last version we saw, returning whether the edit needs a rebuild
+    fn manifest_needs_rebuild(
+        &mut self,
+        manifest_path: &Path,
+        rebuild_on_any_change: bool,
+    ) -> bool {
+        // Like rsx, the manifest may not parse while the user is in the middle of an edit
+        let new = match read_manifest(manifest_path) {
+            Ok(new) => new,
+            Err(err) => {
+                tracing::warn!("Waiting for Cargo.toml to parse before rebuilding: {err}");
+                return false;
+            }
+        };
+
+        let changes = match &self.manifest {
+            Some(old) => manifest_changes(old, &new),
+            None => vec!["Cargo.toml changed".to_string()],
+        };
+        self.manifest = Some(new);
+
+        if changes.is_empty() {
+            if rebuild_on_any_change {
+                tracing::info!("Rebuilding because Cargo.toml changed");
+                return true;
+            }
+            tracing::debug!("Cargo.toml changed, but not in a way that affects the build");
+            return false;
+        }
+
+        tracing::info!("Rebuilding because {}", changes.join(", "));
+        true
+    }
+
+    /// The paths we were asked to watch but couldn't, along with why
+    pub fn failed_paths(&self) -> &[(PathBuf, String)] {
+        &self.failed_paths
+    }
+
+    /// Take the parse diagnostics produced by the last hot reload attempt, keyed by file
+    pub fn take_diagnostics(&mut self) -> Vec<(PathBuf, Vec<HotReloadDiagnostic>)> {
+        std::mem::take(&mut self.pending_diagnostics)
+    }
+
     /// Get any hot reload changes that have been applied since the last full rebuild
     pub fn applied_hot_reload_changes(&mut self) -> Option<HotReloadMsg> {
         self.applied_hot_reload_message.clone()
@@ -282,6 +766,7 @@ impl Watcher {
     /// Clear the hot reload changes. This should be called any time a new build is starting
     pub fn clear_hot_reload_changes(&mut self) {
         self.applied_hot_reload_message.take();
+        self.sent_templates.clear();
     }
 
     /// Store the hot reload changes for any future clients that connect
@@ -303,10 +788,28 @@ impl Watcher {
                 for template in &msg.templates {
                     templates.insert(template.location.clone(), template.clone());
                 }
+                // New clients start from the compiled templates, so cleared templates just stop being replayed
+                for location in &msg.cleared_templates {
+                    templates.remove(location);
+                }
                 assets.extend(msg.assets.iter().cloned());
+                let mut asset_details: HashMap<PathBuf, HotReloadAsset> =
+                    std::mem::take(&mut applied.asset_details)
+                        .into_iter()
+                        .map(|asset| (asset.path.clone(), asset))
+                        .collect();
+                for asset in &msg.asset_details {
+                    asset_details.insert(asset.path.clone(), asset.clone());
+                }
                 unknown_files.extend(msg.unknown_files.iter().cloned());
+                // Replay in a stable order so every new client sees the same sequence of updates
                 applied.templates = templates.into_values().collect();
+                applied
+                    .templates
+                    .sort_by(|a, b| a.location.cmp(&b.location));
                 applied.assets = assets.into_iter().collect();
+                applied.asset_details = asset_details.into_values().collect();
+                applied.asset_details.sort_by(|a, b| a.path.cmp(&b.path));
                 applied.unknown_files = unknown_files.into_iter().collect();
             }
             None => {
@@ -322,87 +825,2648 @@ impl Watcher {
     }
 }
 
-fn is_backup_file(path: PathBuf) -> bool {
-    // If there's a tilde at the end of the file, it's a backup file
-    if let Some(name) = path.file_name() {
-        if let Some(name) = name.to_str() {
-            if name.ends_with('~') {
-                return true;
-            }
+/// How often we check whether a file is still being written
+const SETTLE_CHECK_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Wait until a file's size and modification time are the same across two checks in a row, so we don't read a
+/// file that's halfway through being written. Gives up at `deadline`.
+async fn wait_for_settle(path: &Path, deadline: tokio::time::Instant) {
+    let stat = |path: &Path| {
+        fs::metadata(path)
+            .ok()
+            .map(|metadata| (metadata.len(), metadata.modified().ok()))
+    };
+
+    let mut last = stat(path);
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(SETTLE_CHECK_INTERVAL).await;
+        let current = stat(path);
+        if current == last {
+            return;
         }
+        last = current;
     }
 
-    // if the file is hidden, it's a backup file
-    if let Some(name) = path.file_name() {
-        if let Some(name) = name.to_str() {
-            if name.starts_with('.') {
-                return true;
+    tracing::debug!(
+        "{} was still changing when we ran out of time to wait for it, reading it anyway",
+        path.display()
+    );
+}
+
+/// Find the crate a file belongs to - either one of the watched workspace crates or the app crate itself
+fn owning_crate_dir<'a>(
+    path: &Path,
+    crate_dir: &'a Path,
+    workspace_dirs: &'a [PathBuf],
+) -> &'a Path {
+    // Crates can be nested inside each other, so the deepest crate containing the file owns it
+    workspace_dirs
+        .iter()
+        .map(|dir| dir.as_path())
+        .chain(std::iter::once(crate_dir))
+        .filter(|dir| path.starts_with(dir))
+        .max_by_key(|dir| dir.components().count())
+        .unwrap_or(crate_dir)
+}
+
+fn read_manifest(manifest_path: &Path) -> anyhow::Result<toml::Table> {
+    Ok(toml::from_str(&fs::read_to_string(manifest_path)?)?)
+}
+
+/// The Cargo.toml sections that affect how the app is built
+const BUILD_MANIFEST_SECTIONS: [&str; 10] = [
+    "dependencies",
+    "build-dependencies",
+    "target",
+    "features",
+    "profile",
+    "patch",
+    "replace",
+    "lib",
+    "bin",
+    "example",
+];
+
+/// Describe the differences between two versions of a Cargo.toml that affect the build
+fn manifest_changes(old: &toml::Table, new: &toml::Table) -> Vec<String> {
+    let mut changes = vec![];
+
+    for section in BUILD_MANIFEST_SECTIONS {
+        let (old, new) = (old.get(section), new.get(section));
+        if old == new {
+            continue;
+        }
+
+        // Name the dependencies that changed, since that's what most manifest edits are
+        if section == "dependencies" || section == "build-dependencies" {
+            let empty = toml::Table::new();
+            let old = old.and_then(|deps| deps.as_table()).unwrap_or(&empty);
+            let new = new.and_then(|deps| deps.as_table()).unwrap_or(&empty);
+            let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for name in names {
+                match (old.get(name), new.get(name)) {
+                    (None, Some(_)) => changes.push(format!("dependency `{name}` was added")),
+                    (Some(_), None) => changes.push(format!("dependency `{name}` was removed")),
+                    (old, new) if old != new => {
+                        changes.push(format!("dependency `{name}` changed"))
+                    }
+                    _ => {}
+                }
             }
+            continue;
         }
+
+        changes.push(format!("`[{section}]` changed"));
     }
 
-    false
+    changes
 }
 
-/// Tests if the provided [`notify::Event`] is something we listen to so we can avoid unescessary hot reloads.
-fn is_allowed_notify_event(event: &notify::Event) -> bool {
-    match event.kind {
-        EventKind::Modify(ModifyKind::Data(_)) => true,
-        EventKind::Modify(ModifyKind::Name(_)) => true,
-        EventKind::Create(_) => true,
-        EventKind::Remove(_) => true,
-        // The primary modification event on WSL's poll watcher.
-        EventKind::Modify(ModifyKind::Metadata(MetadataKind::WriteTime)) => true,
-        // Catch-all for unknown event types.
-        EventKind::Modify(ModifyKind::Any) => true,
-        // Don't care about anything else.
-        _ => false,
+/// Find the tracked files whose contents differ from what we last saw, for when we can't trust the events to
+/// have told us about every change.
+///
+/// Files the file map parsed are compared against their source, and other files we've seen change against the
+/// hash of their contents. Tracked files that were deleted count as changed.
+///
+/// Returns how many files were checked along with the ones that changed.
+fn changed_since_last_seen(
+    file_map: &FileMap,
+    content_hashes: &mut ContentHashes,
+) -> (usize, Vec<PathBuf>) {
+    let tracked: BTreeSet<PathBuf> = file_map
+        .map
+        .keys()
+        .chain(content_hashes.0.keys())
+        .cloned()
+        .collect();
+
+    let scanned = tracked.len();
+    let changed = tracked
+        .into_iter()
+        .filter(|path| {
+            let known_contents = file_map.map.get(path).map(|cached| cached.raw.as_str());
+            content_hashes.changed(path, known_contents)
+        })
+        .collect();
+
+    (scanned, changed)
+}
+
+/// The hashes of the contents of files we've seen change, used to skip saves that didn't change anything.
+///
+/// Hashes are recorded lazily as events come in instead of scanning the project up front, so only files that
+/// actually get edited take up space.
+#[derive(Default)]
+struct ContentHashes(HashMap<PathBuf, u64>);
+
+impl ContentHashes {
+    /// Check whether the file's contents changed since we last saw it, recording its new hash.
+    ///
+    /// For files we haven't hashed yet we compare against `known_contents` if we have them (e.g. the source the
+    /// FileMap parsed), and otherwise assume the file changed.
+    fn changed(&mut self, path: &Path, known_contents: Option<&str>) -> bool {
+        let Ok(contents) = fs::read(path) else {
+            // Deleted files always count as a change
+            self.0.remove(path);
+            return true;
+        };
+
+        let hash = hash_contents(&contents);
+        let previous = self
+            .0
+            .insert(path.to_path_buf(), hash)
+            .or_else(|| known_contents.map(|known| hash_contents(known.as_bytes())));
+
+        previous != Some(hash)
     }
 }
 
-const WSL_1: &str = "/proc/sys/kernel/osrelease";
-const WSL_2: &str = "/proc/version";
-const WSL_KEYWORDS: [&str; 2] = ["microsoft", "wsl"];
+fn hash_contents(contents: &[u8]) -> u64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
 
-/// Detects if `dx` is being ran in a WSL environment.
-///
-/// We determine this based on whether the keyword `microsoft` or `wsl` is contained within the [`WSL_1`] or [`WSL_2`] files.
-/// This may fail in the future as it isn't guaranteed by Microsoft.
-/// See https://github.com/microsoft/WSL/issues/423#issuecomment-221627364
-fn is_wsl() -> bool {
-    // Test 1st File
-    if let Ok(content) = fs::read_to_string(WSL_1) {
-        let lowercase = content.to_lowercase();
-        for keyword in WSL_KEYWORDS {
-            if lowercase.contains(keyword) {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Drop the templates that are identical to the version we last sent to clients, and remember the rest as sent
+fn unsent_templates(
+    sent: &mut HashMap<String, u64>,
+    templates: Vec<HotReloadTemplateWithLocation>,
+) -> Vec<HotReloadTemplateWithLocation> {
+    templates
+        .into_iter()
+        .filter(|template| {
+            // If we can't hash the template, we can't tell if it changed, so send it anyway
+            let Ok(serialized) = serde_json::to_vec(&template.template) else {
+                sent.remove(&template.location);
                 return true;
-            }
-        }
+            };
+            let hash = hash_contents(&serialized);
+            sent.insert(template.location.clone(), hash) != Some(hash)
+        })
+        .collect()
+}
+
+/// Convert a hot reload error into the diagnostics we send to clients
+///
+/// Parse errors point at the line and column they start on, along with that line of `src`. Errors reading the file
+/// have no location, so they are reported at 0:0.
+fn diagnostic_from_error(err: &HotreloadError, src: &str) -> Vec<HotReloadDiagnostic> {
+    match err {
+        HotreloadError::Parse(err) => err
+            .clone()
+            .into_iter()
+            .map(|err| diagnostic_from_syn(&err, src))
+            .collect(),
+        HotreloadError::Failure(err) => vec![HotReloadDiagnostic {
+            message: err.to_string(),
+            line: 0,
+            column: 0,
+            snippet: String::new(),
+            category: DiagnosticCategory::IoError,
+        }],
+        HotreloadError::Notreloadable => vec![],
     }
+}
 
-    // Test 2nd File
-    if let Ok(content) = fs::read_to_string(WSL_2) {
-        let lowercase = content.to_lowercase();
-        for keyword in WSL_KEYWORDS {
-            if lowercase.contains(keyword) {
-                return true;
-            }
-        }
+/// Convert a syn error into a diagnostic we can send to clients
+fn diagnostic_from_syn(err: &syn::Error, src: &str) -> HotReloadDiagnostic {
+    let start = err.span().start();
+    let snippet = src
+        .lines()
+        .nth(start.line.saturating_sub(1))
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+
+    HotReloadDiagnostic {
+        message: err.to_string(),
+        line: start.line,
+        column: start.column + 1,
+        snippet,
+        category: DiagnosticCategory::ParseError,
     }
+}
 
-    false
+/// The changed files in a batch, sorted by how we handle them
+#[derive(Debug, Default, PartialEq)]
+struct ChangedFiles {
+    /// Rust files we attempt to hot reload the rsx of
+    rust: Vec<PathBuf>,
+    /// Files in the asset directory that clients can reload in place
+    assets: Vec<PathBuf>,
+    /// Files the user asked us to rebuild the app for
+    rebuild: Vec<PathBuf>,
+    /// Anything else - best of luck!
+    unknown: Vec<PathBuf>,
 }
 
-#[test]
-fn test_is_backup_file() {
-    assert!(is_backup_file(PathBuf::from("examples/test.rs~")));
-    assert!(is_backup_file(PathBuf::from("examples/.back")));
-    assert!(is_backup_file(PathBuf::from("test.rs~")));
-    assert!(is_backup_file(PathBuf::from(".back")));
-
-    assert!(!is_backup_file(PathBuf::from("val.rs")));
-    assert!(!is_backup_file(PathBuf::from(
-        "/Users/jonkelley/Development/Tinkering/basic_05_example/src/lib.rs"
-    )));
-    assert!(!is_backup_file(PathBuf::from("exmaples/val.rs")));
+impl ChangedFiles {
+    fn sort(
+        files: Vec<PathBuf>,
+        asset_dir: &Path,
+        asset_dirs: &[PathBuf],
+        policies: &HashMap<String, ExtensionPolicy>,
+    ) -> Self {
+        let mut changed = Self::default();
+
+        for path in files {
+            // The extra asset dirs only contain assets, so they skip the extension handling entirely
+            if asset_dirs.iter().any(|dir| path.starts_with(dir)) {
+                changed.assets.push(path);
+                continue;
+            }
+
+            // for various assets that might be linked in, we just try to hotreloading them forcefully
+            // That is, unless they appear in an include! macro, in which case we need to a full rebuild....
+            let Some(ext) = path.extension().and_then(|v| v.to_str()) else {
+                continue;
+            };
+
+            match extension_policy(ext, &path, asset_dir, policies) {
+                Some(ExtensionPolicy::Rsx) => changed.rust.push(path),
+                Some(ExtensionPolicy::Asset) => changed.assets.push(path),
+                Some(ExtensionPolicy::Rebuild) => changed.rebuild.push(path),
+                Some(ExtensionPolicy::Ignore) => {}
+                None => changed.unknown.push(path),
+            }
+        }
+
+        changed
+    }
+}
+
+/// Describe a changed asset for clients.
+///
+/// The path is made relative to the first of `roots` that contains it. Assets are hashed as raw bytes since
+/// they're often binary, and assets larger than `max_hash_size` aren't hashed at all.
+fn asset_details(path: &Path, roots: &[&Path], max_hash_size: u64) -> HotReloadAsset {
+    let relative = roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .to_path_buf();
+
+    let size = fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    let hash = match size <= max_hash_size {
+        true => fs::read(path)
+            .ok()
+            .map(|contents| format!("{:016x}", hash_contents(&contents))),
+        false => None,
+    };
+
+    HotReloadAsset {
+        path: relative,
+        size,
+        hash,
+    }
+}
+
+/// Look up how to handle a changed file. Extensions the user configured win over the defaults.
+fn extension_policy(
+    ext: &str,
+    path: &Path,
+    asset_dir: &Path,
+    policies: &HashMap<String, ExtensionPolicy>,
+) -> Option<ExtensionPolicy> {
+    if let Some(policy) = policies.get(ext) {
+        return Some(*policy);
+    }
+
+    match ext {
+        "rs" => Some(ExtensionPolicy::Rsx),
+        // Clients reload every stylesheet on the page when they get an asset update, so css can be hot
+        // reloaded wherever it lives
+        "css" => Some(ExtensionPolicy::Asset),
+        _ if path.starts_with(asset_dir) => Some(ExtensionPolicy::Asset),
+        _ => None,
+    }
+}
+
+/// The notify watchers feeding the event channel.
+///
+/// Paths are registered with the native watcher. On Linux, big projects can run out of inotify watches, so any
+/// path the native watcher can't take is polled instead. Both watchers send to the same channel.
+struct FileWatchers {
+    native: Box<dyn notify::Watcher>,
+    /// Created the first time the native watcher runs out of watches
+    poll: Option<Box<dyn notify::Watcher>>,
+    tx: UnboundedSender<notify::Event>,
+    use_poll_watcher: bool,
+    poll_interval: Duration,
+    /// The paths we're watching
+    roots: Vec<PathBuf>,
+    /// The paths we want to watch that don't exist right now
+    missing: Vec<PathBuf>,
+    last_recreated: Option<Instant>,
+}
+
+impl FileWatchers {
+    /// Recursively watch a path, falling back to polling if the native watcher hit the watch limit
+    ///
+    /// Paths that don't exist are remembered and registered by [`Self::refresh`] once they're created.
+    fn watch(&mut self, path: &Path) -> notify::Result<()> {
+        // The poll watcher happily "watches" paths that don't exist, so check for ourselves
+        if !path.exists() {
+            self.missing.push(path.to_path_buf());
+            return Err(notify::Error::path_not_found().add_path(path.to_path_buf()));
+        }
+
+        let result = self.watch_with_fallback(path);
+        match &result {
+            Ok(()) => self.roots.push(path.to_path_buf()),
+            Err(err) if is_not_found_error(err) => self.missing.push(path.to_path_buf()),
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn watch_with_fallback(&mut self, path: &Path) -> notify::Result<()> {
+        let mode = notify::RecursiveMode::Recursive;
+
+        let err = match self.native.watch(path, mode) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        if !is_watch_limit_error(&err) {
+            return Err(err);
+        }
+
+        if self.poll.is_none() {
+            tracing::warn!(
+                "Ran out of inotify watches, so some files will be polled every {}s instead. \
+                To watch them natively, raise the limit with `sudo sysctl fs.inotify.max_user_watches=524288` \
+                (add it to /etc/sysctl.conf to keep it across reboots)",
+                self.poll_interval.as_secs()
+            );
+            self.poll = Some(poll_watcher(
+                notify_event_handler(self.tx.clone()),
+                self.poll_interval,
+            )?);
+        }
+
+        tracing::debug!("Polling {} for changes", path.display());
+        self.poll.as_mut().unwrap().watch(path, mode)
+    }
+
+    /// Replace the watchers with new ones watching the same paths, after the old ones reported an error.
+    ///
+    /// Some errors are reported over and over (e.g. a file we can't read), so we only do this every so often.
+    fn recreate(&mut self) {
+        if self
+            .last_recreated
+            .is_some_and(|last| last.elapsed() < WATCHER_RECREATE_INTERVAL)
+        {
+            return;
+        }
+        self.last_recreated = Some(Instant::now());
+
+        let native = match create_watcher(
+            notify_event_handler(self.tx.clone()),
+            self.use_poll_watcher,
+            self.poll_interval,
+        ) {
+            Ok(native) => native,
+            Err(err) => {
+                tracing::error!("Failed to recreate the file watcher: {err}");
+                return;
+            }
+        };
+
+        tracing::info!("Recreating the file watcher after an error");
+        self.native = native;
+        self.poll = None;
+        for root in std::mem::take(&mut self.roots) {
+            if let Err(err) = self.watch(&root) {
+                tracing::warn!("Failed to watch path: {}", err);
+            }
+        }
+    }
+
+    /// Stop watching a path we registered with [`Self::watch`]
+    fn unwatch(&mut self, path: &Path) {
+        if self.roots.iter().any(|root| root == path) {
+            _ = self.native.unwatch(path);
+            if let Some(poll) = &mut self.poll {
+                _ = poll.unwatch(path);
+            }
+        }
+        self.roots.retain(|root| root != path);
+        self.missing.retain(|missing| missing != path);
+    }
+
+    /// Whether a path is inside one of the paths we're watching
+    fn covers(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| path.starts_with(root))
+    }
+
+    fn has_missing(&self) -> bool {
+        !self.missing.is_empty()
+    }
+
+    /// Re-register the roots in `touched` (notify drops the watch when a watched path is removed or renamed
+    /// over), and register any missing roots that exist now. Returns the roots that started being watched.
+    fn refresh(&mut self, touched: &[PathBuf]) -> Vec<PathBuf> {
+        let (stale, roots): (Vec<PathBuf>, Vec<PathBuf>) = std::mem::take(&mut self.roots)
+            .into_iter()
+            .partition(|root| touched.contains(root) || !root.exists());
+        self.roots = roots;
+
+        for root in stale {
+            _ = self.native.unwatch(&root);
+            if let Some(poll) = &mut self.poll {
+                _ = poll.unwatch(&root);
+            }
+            self.missing.push(root);
+        }
+
+        let mut watched = vec![];
+        for path in std::mem::take(&mut self.missing) {
+            if !path.exists() {
+                self.missing.push(path);
+                continue;
+            }
+
+            match self.watch(&path) {
+                Ok(()) => watched.push(path),
+                Err(err) => tracing::warn!("Failed to watch path: {}", err),
+            }
+        }
+
+        watched
+    }
+}
+
+/// Register all the paths we want to watch, collecting the ones that failed instead of logging each one.
+///
+/// Paths that don't exist yet aren't failures, since they're watched once they're created. If every path
+/// failed, hot reloading can't work at all, so that's an error.
+fn register_watch_paths(
+    watchers: &mut FileWatchers,
+    paths: &[PathBuf],
+) -> Result<Vec<(PathBuf, String)>> {
+    let mut failed = vec![];
+
+    for path in paths {
+        match watchers.watch(path) {
+            Ok(()) => {}
+            Err(err) if is_not_found_error(&err) => tracing::debug!(
+                "{} doesn't exist yet, it will be watched once it's created",
+                path.display()
+            ),
+            Err(err) => failed.push((path.clone(), err.to_string())),
+        }
+    }
+
+    if failed.is_empty() {
+        return Ok(failed);
+    }
+
+    let summary = failed
+        .iter()
+        .map(|(path, err)| format!("  {}: {err}", path.display()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if failed.len() == paths.len() {
+        return Err(crate::Error::WatchPathsFailed(summary));
+    }
+
+    tracing::warn!(
+        "Failed to watch {} of {} paths, so changes to them won't hot reload:\n{summary}",
+        failed.len(),
+        paths.len()
+    );
+
+    Ok(failed)
+}
+
+/// Create events for every file under a root that just started being watched, since we didn't hear about them
+/// being created
+fn created_events(root: &Path) -> Vec<notify::Event> {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| {
+            notify::Event::new(EventKind::Create(CreateKind::File)).add_path(entry.into_path())
+        })
+        .collect()
+}
+
+/// How long we wait before recreating the watchers again after an error
+const WATCHER_RECREATE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The info attached to the rescan events we send when the watcher reports an error
+const WATCHER_ERROR: &str = "watcher error";
+
+/// How often we check whether watch paths that didn't exist have been created
+const MISSING_PATH_RETRY: Duration = Duration::from_secs(2);
+
+/// Whether notify failed because the path doesn't exist
+fn is_not_found_error(err: &notify::Error) -> bool {
+    match &err.kind {
+        notify::ErrorKind::PathNotFound => true,
+        notify::ErrorKind::Io(err) => err.kind() == std::io::ErrorKind::NotFound,
+        _ => false,
+    }
+}
+
+/// Whether notify failed because the system limit on watches (`fs.inotify.max_user_watches`) was reached
+fn is_watch_limit_error(err: &notify::Error) -> bool {
+    match &err.kind {
+        notify::ErrorKind::MaxFilesWatch => true,
+        // inotify reports the limit as ENOSPC
+        #[cfg(target_os = "linux")]
+        notify::ErrorKind::Io(err) => err.raw_os_error() == Some(28),
+        _ => false,
+    }
+}
+
+/// Build the event handler for notify, forwarding the events we care about to the channel.
+///
+/// Notify tells us when it dropped events (e.g. the inotify queue overflowed) with a rescan event. Errors mean
+/// we may have missed events too, so they're forwarded as a rescan event tagged with [`WATCHER_ERROR`].
+fn notify_event_handler(
+    tx: UnboundedSender<notify::Event>,
+) -> impl FnMut(notify::Result<notify::Event>) + Clone + Send + 'static {
+    move |info: notify::Result<notify::Event>| match info {
+        Ok(e) if is_allowed_notify_event(&e) || e.need_rescan() => {
+            _ = tx.unbounded_send(e);
+        }
+        Ok(_) => {}
+        Err(err) => {
+            tracing::warn!("File watcher error: {err}");
+            let event = notify::Event::new(EventKind::Other)
+                .set_flag(notify::event::Flag::Rescan)
+                .set_info(WATCHER_ERROR);
+            _ = tx.unbounded_send(event);
+        }
+    }
+}
+
+/// Create the notify watcher, either polling at `poll_interval` or using the platform's native events.
+///
+/// If the native watcher can't be created we fall back to polling so hot reloading keeps working.
+fn create_watcher<F>(
+    event_handler: F,
+    use_poll_watcher: bool,
+    poll_interval: Duration,
+) -> notify::Result<Box<dyn notify::Watcher>>
+where
+    F: notify::EventHandler + Clone,
+{
+    if use_poll_watcher {
+        return poll_watcher(event_handler, poll_interval);
+    }
+
+    match notify::recommended_watcher(event_handler.clone()) {
+        Ok(watcher) => Ok(Box::new(watcher)),
+        Err(err) => {
+            tracing::warn!(
+                "Failed to create the native file watcher ({err}), falling back to polling every {}s",
+                poll_interval.as_secs()
+            );
+            poll_watcher(event_handler, poll_interval)
+        }
+    }
+}
+
+fn poll_watcher(
+    event_handler: impl notify::EventHandler,
+    poll_interval: Duration,
+) -> notify::Result<Box<dyn notify::Watcher>> {
+    Ok(Box::new(notify::PollWatcher::new(
+        event_handler,
+        Config::default().with_poll_interval(poll_interval),
+    )?))
+}
+
+/// Keep pulling events off the channel until no new event has arrived for `window`, or until `deadline` passes.
+///
+/// The window is shared by every path in the batch: an event for any path restarts it.
+///
+/// Cancelling this future is safe: any events received so far have already been pushed into `queue`.
+async fn debounce_events(
+    rx: &mut UnboundedReceiver<notify::Event>,
+    queue: &mut Vec<notify::Event>,
+    window: Duration,
+    deadline: tokio::time::Instant,
+) {
+    if window.is_zero() {
+        while let Ok(Some(event)) = rx.try_next() {
+            queue_event(queue, event);
+        }
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            event = rx.next() => match event {
+                Some(event) => queue_event(queue, event),
+                None => return,
+            },
+            _ = tokio::time::sleep(window) => return,
+            _ = tokio::time::sleep_until(deadline) => {
+                tracing::debug!("Files are still changing, handling the changes so far");
+                return;
+            }
+        }
+    }
+}
+
+/// Add an event to the queue, collapsing it into the previous event if both are content or metadata
+/// modifications of the same paths.
+///
+/// Many editors report a single save as several modify events (metadata, then data, ...). We handle those
+/// the same way, so this keeps the queue small even when debouncing is off. Renames are always kept since
+/// they can move a directory we need to rewatch.
+fn queue_event(queue: &mut Vec<notify::Event>, event: notify::Event) {
+    let is_modify = |event: &notify::Event| {
+        matches!(
+            event.kind,
+            EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Metadata(_) | ModifyKind::Any)
+        )
+    };
+
+    if let Some(last) = queue.last() {
+        if is_modify(last) && is_modify(&event) && last.paths == event.paths {
+            return;
+        }
+    }
+
+    queue.push(event);
+}
+
+/// Whether a batch of events touches more than `threshold` distinct paths
+fn is_event_storm(events: &[notify::Event], threshold: usize) -> bool {
+    let paths: HashSet<&PathBuf> = events
+        .iter()
+        .filter(|event| is_allowed_notify_event(event))
+        .flat_map(|event| event.paths.iter())
+        .collect();
+
+    paths.len() > threshold
+}
+
+/// Collect the paths touched by a batch of events, keeping only the first occurrence of each path
+fn changed_paths(events: impl IntoIterator<Item = notify::Event>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    let mut paths = vec![];
+
+    for event in events {
+        // We only care about certain events.
+        if !is_allowed_notify_event(&event) {
+            continue;
+        }
+
+        for path in event.paths {
+            if seen.insert(path.clone()) {
+                paths.push(path);
+            }
+        }
+    }
+
+    paths
+}
+
+/// Maps the canonical paths notify reports back to the paths cargo gave us for the crate and workspace members.
+///
+/// The ignore rules, the file map, and the asset dir are all rooted at the directories cargo reports. If one of
+/// those is reached through a symlink, the events for it won't start with it, and nothing would match. On
+/// case-insensitive filesystems the events may also spell the root with different casing.
+struct PathAliases {
+    /// (canonical root, root as cargo reports it), deepest root first
+    roots: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PathAliases {
+    fn new<'a>(roots: impl IntoIterator<Item = &'a PathBuf>) -> Self {
+        let mut roots: Vec<(PathBuf, PathBuf)> = roots
+            .into_iter()
+            .map(|root| {
+                let canonical = root.canonicalize().unwrap_or_else(|_| root.clone());
+                (canonical, root.clone())
+            })
+            .collect();
+        roots.sort_by_key(|(canonical, _)| std::cmp::Reverse(canonical.components().count()));
+        Self { roots }
+    }
+
+    /// Rewrite the paths into the form cargo uses, dropping paths that resolve to a file we've already seen under
+    /// another name (e.g. through a symlinked directory inside the project)
+    fn normalize(&self, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+
+        paths
+            .into_iter()
+            .map(|path| self.alias(path))
+            .filter(|path| seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())))
+            .collect()
+    }
+
+    fn alias(&self, path: PathBuf) -> PathBuf {
+        for (canonical, root) in &self.roots {
+            let rest = strip_path_prefix(&path, canonical, CASE_INSENSITIVE_FS)
+                .or_else(|| strip_path_prefix(&path, root, CASE_INSENSITIVE_FS));
+            if let Some(rest) = rest {
+                return root.join(rest);
+            }
+        }
+
+        path
+    }
+}
+
+/// Whether the filesystem treats paths that only differ in case as the same path. Windows and macOS do by
+/// default, Linux doesn't.
+const CASE_INSENSITIVE_FS: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Strip `prefix` from `path` like [`Path::strip_prefix`], optionally comparing the components without case
+fn strip_path_prefix<'a>(path: &'a Path, prefix: &Path, ignore_case: bool) -> Option<&'a Path> {
+    if !ignore_case {
+        return path.strip_prefix(prefix).ok();
+    }
+
+    let mut components = path.components();
+    for expected in prefix.components() {
+        let actual = components.next()?;
+        let fold = |component: std::path::Component| {
+            component.as_os_str().to_string_lossy().to_lowercase()
+        };
+        if fold(actual) != fold(expected) {
+            return None;
+        }
+    }
+
+    Some(components.as_path())
+}
+
+/// The directories we know we never want to watch, unless the user opts out with `default_excludes = false`
+pub(crate) fn default_excludes(out_dir: &Path) -> Vec<String> {
+    vec![
+        ".git".to_string(),
+        ".github".to_string(),
+        ".vscode".to_string(),
+        "target".to_string(),
+        "node_modules".to_string(),
+        "dist".to_string(),
+        out_dir.display().to_string(),
+    ]
+}
+
+/// A gitignore-syntax file in the crate root that only applies to the watcher
+const DIOXUS_IGNORE: &str = ".dioxusignore";
+
+/// The files in the crate root that [`build_ignore`] reads exclusion rules from
+const IGNORE_FILES: [&str; 2] = [".gitignore", DIOXUS_IGNORE];
+
+/// Whether a changed path is one of the files we read ignore rules from, including `.gitignore` files in
+/// subdirectories. This goes by file name so ignore files created after startup are recognized too. Changes to
+/// these rebuild the matcher instead of triggering a hot reload or rebuild.
+fn is_ignore_file(path: &Path, crate_dir: &Path) -> bool {
+    path.starts_with(crate_dir)
+        && path
+            .file_name()
+            .is_some_and(|name| IGNORE_FILES.iter().any(|ignore_file| name == *ignore_file))
+}
+
+/// All the rules that exclude paths from the watcher.
+///
+/// `.gitignore` files in subdirectories only apply beneath the directory they live in, so each gets its own
+/// matcher. The crate-level rules from [`build_ignore`] are checked first and win when they match, so
+/// `exclude_path` and `.dioxusignore` keep the final say. Otherwise the deepest nested `.gitignore` with a
+/// matching pattern decides, like it does for git.
+///
+/// Cargo's target dir is always excluded along with the default excludes, wherever it lives. Nothing in there
+/// (like the `.rs` files build scripts generate in their `OUT_DIR`) is source we want to hot reload.
+struct IgnoreRules {
+    root: Gitignore,
+    nested: Vec<Gitignore>,
+    target_dir: Option<(PathBuf, Gitignore)>,
+}
+
+impl IgnoreRules {
+    fn new(
+        crate_dir: &Path,
+        out_dir: &Path,
+        target_dir: &Path,
+        watcher_config: &WebWatcherConfig,
+    ) -> Self {
+        let nested = match watcher_config.respect_gitignore {
+            true => nested_gitignores(crate_dir),
+            false => vec![],
+        };
+
+        let target_dir = match watcher_config.default_excludes {
+            true => build_dir_ignore(target_dir).map(|ignore| (target_dir.to_path_buf(), ignore)),
+            false => None,
+        };
+
+        Self {
+            root: build_ignore(crate_dir, out_dir, watcher_config),
+            nested,
+            target_dir,
+        }
+    }
+
+    fn matched(&self, path: &Path, is_dir: bool) -> Match<&Glob> {
+        if let Some((target_dir, ignore)) = &self.target_dir {
+            if path.starts_with(target_dir) {
+                return ignore.matched_path_or_any_parents(path, is_dir);
+            }
+        }
+
+        let matched = self.root.matched(path, is_dir);
+        if !matched.is_none() {
+            return matched;
+        }
+
+        self.nested
+            .iter()
+            .filter(|ignore| strip_path_prefix(path, ignore.path(), CASE_INSENSITIVE_FS).is_some())
+            .map(|ignore| ignore.matched(path, is_dir))
+            .find(|matched| !matched.is_none())
+            .unwrap_or(Match::None)
+    }
+}
+
+/// Build a matcher for a directory and everything in it, wherever it is
+fn build_dir_ignore(dir: &Path) -> Option<Gitignore> {
+    let (parent, name) = (dir.parent()?, dir.file_name()?);
+    let mut builder = GitignoreBuilder::new(parent);
+    builder.case_insensitive(CASE_INSENSITIVE_FS).ok()?;
+    builder
+        .add_line(None, &format!("/{}/", name.to_string_lossy()))
+        .ok()?;
+    builder.build().ok()
+}
+
+/// Load the `.gitignore` files in the subdirectories of the crate, deepest first.
+///
+/// This runs once when the watcher starts (and when an ignore file changes) rather than per event. The walk
+/// itself respects the ignore files it finds, so it doesn't descend into `target` and friends.
+fn nested_gitignores(crate_dir: &Path) -> Vec<Gitignore> {
+    let mut nested: Vec<Gitignore> = ignore::WalkBuilder::new(crate_dir)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build()
+        .flatten()
+        .filter(|entry| {
+            entry.file_name() == ".gitignore" && entry.path().parent() != Some(crate_dir)
+        })
+        .map(|entry| {
+            let dir = entry.path().parent().unwrap_or(crate_dir);
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.case_insensitive(CASE_INSENSITIVE_FS).unwrap();
+            if let Some(err) = builder.add(entry.path()) {
+                tracing::warn!("Failed to load {}: {err}", entry.path().display());
+            }
+            builder.build().unwrap_or_else(|_| Gitignore::empty())
+        })
+        .collect();
+
+    nested.sort_by_key(|ignore| std::cmp::Reverse(ignore.path().components().count()));
+    nested
+}
+
+/// Build the matcher for paths the watcher should never report, using `.gitignore` semantics.
+///
+/// The rules are applied in this order, and later patterns win:
+/// 1. The crate's `.gitignore` (unless `respect_gitignore` is off)
+/// 2. The directories from [`default_excludes`] (unless `default_excludes` is off)
+/// 3. The user's `exclude_path` patterns
+/// 4. The crate's `.dioxusignore`, which can re-include (`!pattern`) anything excluded before it
+fn build_ignore(crate_dir: &Path, out_dir: &Path, watcher_config: &WebWatcherConfig) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(crate_dir);
+    builder.case_insensitive(CASE_INSENSITIVE_FS).unwrap();
+    if watcher_config.respect_gitignore {
+        builder.add(crate_dir.join(".gitignore"));
+    }
+
+    if watcher_config.default_excludes {
+        // Gitignore patterns are relative to the crate, but the out dir is absolute and may only share a prefix
+        // with the crate dir once both are canonicalized
+        let out_dir = out_dir
+            .strip_prefix(crate_dir)
+            .ok()
+            .map(Path::to_path_buf)
+            .or_else(|| {
+                let out_dir = out_dir.canonicalize().ok()?;
+                Some(
+                    out_dir
+                        .strip_prefix(crate_dir.canonicalize().ok()?)
+                        .ok()?
+                        .to_path_buf(),
+                )
+            })
+            .unwrap_or_else(|| out_dir.to_path_buf());
+        for path in default_excludes(&out_dir) {
+            builder
+                .add_line(None, &path)
+                .expect("failed to add path to file excluder");
+        }
+    }
+
+    for pattern in &watcher_config.exclude_path {
+        if let Err(err) = builder.add_line(None, pattern) {
+            tracing::warn!("Invalid exclude_path pattern {pattern:?}: {err}");
+        }
+    }
+
+    if let Some(err) = builder.add(crate_dir.join(DIOXUS_IGNORE)) {
+        // A missing .dioxusignore is the common case and not worth reporting
+        if crate_dir.join(DIOXUS_IGNORE).exists() {
+            tracing::warn!("Failed to load {DIOXUS_IGNORE}: {err}");
+        }
+    }
+
+    builder.build().unwrap()
+}
+
+/// Build a matcher that only lets through paths matching one of the `watch_path` entries.
+///
+/// Plain paths like `src` match everything beneath them, so they keep working alongside globs.
+fn build_watch_filter(crate_dir: &Path, watch_path: &[PathBuf]) -> Override {
+    let mut builder = OverrideBuilder::new(crate_dir);
+    builder.case_insensitive(CASE_INSENSITIVE_FS).unwrap();
+
+    for path in watch_path {
+        let pattern = path.to_string_lossy().replace('\\', "/");
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+        let mut patterns = vec![pattern.to_string()];
+        if !is_glob(path) {
+            patterns.push(format!("{pattern}/**"));
+        }
+
+        for pattern in patterns {
+            if let Err(err) = builder.add(&pattern) {
+                tracing::warn!("Invalid watch_path pattern {pattern:?}: {err}");
+            }
+        }
+    }
+
+    builder.build().unwrap_or_else(|err| {
+        tracing::warn!("Failed to build watch_path filter: {err}");
+        Override::empty()
+    })
+}
+
+/// Checks if a watch path contains any glob syntax
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '[', '{'])
+}
+
+/// The literal directory a glob pattern is rooted in - this is what we register with notify.
+///
+/// For a plain path this is the path itself. A pattern like `**/*.rs` is rooted at the crate dir.
+fn glob_root(path: &Path) -> PathBuf {
+    path.components()
+        .take_while(|component| !is_glob(Path::new(component.as_os_str())))
+        .collect()
+}
+
+/// Whether a path is a backup file or a hidden file. With `editor_ignores`, the swap, lock, and temp files that
+/// common editors write next to the file being edited count too.
+fn is_backup_file(path: PathBuf, editor_ignores: bool) -> bool {
+    // If there's a tilde at the end of the file, it's a backup file
+    if let Some(name) = path.file_name() {
+        if let Some(name) = name.to_str() {
+            if name.ends_with('~') {
+                return true;
+            }
+        }
+    }
+
+    // Swap, lock, and temp files that editors write next to the file being edited
+    if let Some(name) = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|_| editor_ignores)
+    {
+        // vim swap files (.swp, .swo, .swx, ...) and kate swap files
+        if [".swp", ".swo", ".swx", ".kate-swp"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+        {
+            return true;
+        }
+
+        // emacs autosave files (#foo.rs#)
+        if name.starts_with('#') && name.ends_with('#') {
+            return true;
+        }
+
+        // JetBrains safe-write temp files (foo.rs___jb_tmp___)
+        if name.ends_with("___jb_tmp___") || name.ends_with("___jb_old___") {
+            return true;
+        }
+
+        // vim checks if a directory is writable by creating a file called 4913, counting up by 123 until it
+        // finds a free name. Other numeric names (like `assets/404`) are real files
+        let is_vim_write_test = name.bytes().all(|b| b.is_ascii_digit())
+            && !name.starts_with('0')
+            && name
+                .parse::<u64>()
+                .is_ok_and(|number| number >= 4913 && (number - 4913) % 123 == 0);
+        if is_vim_write_test {
+            return true;
+        }
+    }
+
+    // if the file is hidden, it's a backup file
+    if let Some(name) = path.file_name() {
+        if let Some(name) = name.to_str() {
+            if name.starts_with('.') {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Whether a changed path no longer exists and was never tracked - i.e. an editor's temp file that was
+/// renamed over the real file. Missing files we *were* tracking were deleted and still need handling.
+fn is_transient_file(path: &Path, file_map: &FileMap) -> bool {
+    !path.exists() && !file_map.map.contains_key(path)
+}
+
+/// Tests if the provided [`notify::Event`] is something we listen to so we can avoid unescessary hot reloads.
+fn is_allowed_notify_event(event: &notify::Event) -> bool {
+    match event.kind {
+        EventKind::Modify(ModifyKind::Data(_)) => true,
+        EventKind::Modify(ModifyKind::Name(_)) => true,
+        EventKind::Create(_) => true,
+        EventKind::Remove(_) => true,
+        // The primary modification event on WSL's poll watcher.
+        EventKind::Modify(ModifyKind::Metadata(MetadataKind::WriteTime)) => true,
+        // Catch-all for unknown event types.
+        EventKind::Modify(ModifyKind::Any) => true,
+        // Don't care about anything else.
+        _ => false,
+    }
+}
+
+const WSL_1: &str = "/proc/sys/kernel/osrelease";
+const WSL_2: &str = "/proc/version";
+const WSL_KEYWORDS: [&str; 2] = ["microsoft", "wsl"];
+
+/// Detects if `dx` is being ran in a WSL environment.
+///
+/// We determine this based on whether the keyword `microsoft` or `wsl` is contained within the [`WSL_1`] or [`WSL_2`] files.
+/// This may fail in the future as it isn't guaranteed by Microsoft.
+/// See https://github.com/microsoft/WSL/issues/423#issuecomment-221627364
+fn is_wsl() -> bool {
+    // Test 1st File
+    if let Ok(content) = fs::read_to_string(WSL_1) {
+        let lowercase = content.to_lowercase();
+        for keyword in WSL_KEYWORDS {
+            if lowercase.contains(keyword) {
+                return true;
+            }
+        }
+    }
+
+    // Test 2nd File
+    if let Ok(content) = fs::read_to_string(WSL_2) {
+        let lowercase = content.to_lowercase();
+        for keyword in WSL_KEYWORDS {
+            if lowercase.contains(keyword) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+#[test]
+fn test_is_backup_file() {
+    assert!(is_backup_file(PathBuf::from("examples/test.rs~"), true));
+    assert!(is_backup_file(PathBuf::from("examples/.back"), true));
+    assert!(is_backup_file(PathBuf::from("test.rs~"), true));
+    assert!(is_backup_file(PathBuf::from(".back"), true));
+
+    // vim
+    assert!(is_backup_file(PathBuf::from("src/main.rs.swp"), true));
+    assert!(is_backup_file(PathBuf::from("src/.main.rs.swo"), true));
+    assert!(is_backup_file(PathBuf::from("src/4913"), true));
+    // emacs
+    assert!(is_backup_file(PathBuf::from("src/#main.rs#"), true));
+    assert!(is_backup_file(PathBuf::from("src/.#main.rs"), true));
+    // JetBrains
+    assert!(is_backup_file(
+        PathBuf::from("src/main.rs___jb_tmp___"),
+        true
+    ));
+    assert!(is_backup_file(
+        PathBuf::from("src/main.rs___jb_old___"),
+        true
+    ));
+    // kate
+    assert!(is_backup_file(PathBuf::from("src/main.rs.kate-swp"), true));
+
+    assert!(!is_backup_file(PathBuf::from("val.rs"), true));
+    assert!(!is_backup_file(
+        PathBuf::from("/Users/jonkelley/Development/Tinkering/basic_05_example/src/lib.rs"),
+        true
+    ));
+    assert!(!is_backup_file(PathBuf::from("exmaples/val.rs"), true));
+    assert!(!is_backup_file(PathBuf::from("assets/404.html"), true));
+    assert!(!is_backup_file(PathBuf::from("src/v2"), true));
+
+    // vim's write test counts up from 4913 in steps of 123, other numeric names are real files
+    assert!(is_backup_file(PathBuf::from("src/5036"), true));
+    assert!(!is_backup_file(PathBuf::from("assets/404"), true));
+    assert!(!is_backup_file(PathBuf::from("data/2024"), true));
+    assert!(!is_backup_file(PathBuf::from("data/4914"), true));
+    assert!(!is_backup_file(PathBuf::from("data/04913"), true));
+}
+
+#[test]
+fn test_default_editor_ignores_opt_out() {
+    // With the editor list turned off, editor artifacts are treated like any other file
+    assert!(!is_backup_file(PathBuf::from("src/main.rs.swp"), false));
+    assert!(!is_backup_file(PathBuf::from("src/4913"), false));
+    assert!(!is_backup_file(PathBuf::from("src/#main.rs#"), false));
+    assert!(!is_backup_file(
+        PathBuf::from("src/main.rs___jb_tmp___"),
+        false
+    ));
+
+    // Backups and hidden files are always skipped
+    assert!(is_backup_file(PathBuf::from("src/main.rs~"), false));
+    assert!(is_backup_file(PathBuf::from("src/.#main.rs"), false));
+}
+
+#[cfg(test)]
+impl FileWatchers {
+    /// Wrap a watcher whose events go to `tx`, without watching anything yet
+    fn for_test(native: Box<dyn notify::Watcher>, tx: UnboundedSender<notify::Event>) -> Self {
+        Self {
+            native,
+            poll: None,
+            tx,
+            use_poll_watcher: false,
+            poll_interval: Duration::from_millis(50),
+            roots: vec![],
+            missing: vec![],
+            last_recreated: None,
+        }
+    }
+}
+
+/// A debounce deadline that tests won't reach
+#[cfg(test)]
+fn far_deadline() -> tokio::time::Instant {
+    tokio::time::Instant::now() + Duration::from_secs(60)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_debounce_coalesces_rapid_saves() {
+    use notify::event::DataChange;
+
+    let (tx, mut rx) = futures_channel::mpsc::unbounded();
+    let save = || {
+        notify::Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path(PathBuf::from("src/main.rs"))
+    };
+
+    // The second save lands well inside the debounce window of the first
+    tx.unbounded_send(save()).unwrap();
+    let second_save = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        tx.unbounded_send(save()).unwrap();
+        tx
+    });
+
+    let start = tokio::time::Instant::now();
+    let mut queue = vec![rx.next().await.unwrap()];
+    debounce_events(
+        &mut rx,
+        &mut queue,
+        Duration::from_millis(100),
+        far_deadline(),
+    )
+    .await;
+    drop(second_save.await.unwrap());
+
+    // The second save restarted the window
+    assert_eq!(start.elapsed(), Duration::from_millis(110));
+
+    // Both saves modify the same file, so they collapse into one event
+    assert_eq!(queue.len(), 1);
+    assert_eq!(changed_paths(queue), vec![PathBuf::from("src/main.rs")]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_debounce_stops_at_the_deadline() {
+    let (tx, mut rx) = futures_channel::mpsc::unbounded();
+
+    // Something like a log file that's written to more often than the debounce window
+    let writer = tokio::spawn(async move {
+        for i in 0.. {
+            let event = notify::Event::new(EventKind::Modify(ModifyKind::Any))
+                .add_path(PathBuf::from(format!("logs/{i}.log")));
+            if tx.unbounded_send(event).is_err() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(7)).await;
+        }
+    });
+
+    let start = tokio::time::Instant::now();
+    let mut queue = vec![];
+    debounce_events(
+        &mut rx,
+        &mut queue,
+        Duration::from_millis(50),
+        start + Duration::from_millis(200),
+    )
+    .await;
+    writer.abort();
+
+    assert_eq!(start.elapsed(), Duration::from_millis(200));
+    // One event every 7ms from the start until the deadline
+    assert_eq!(queue.len(), 29);
+}
+
+#[test]
+fn test_glob_root() {
+    assert_eq!(glob_root(Path::new("src")), PathBuf::from("src"));
+    assert_eq!(glob_root(Path::new("src/**/*.rs")), PathBuf::from("src"));
+    assert_eq!(
+        glob_root(Path::new("assets/*.css")),
+        PathBuf::from("assets")
+    );
+    assert_eq!(glob_root(Path::new("**/*.rs")), PathBuf::new());
+    assert_eq!(glob_root(Path::new("**")), PathBuf::new());
+}
+
+#[test]
+fn test_watch_filter_globs() {
+    let root = PathBuf::from("/project");
+    let filter = build_watch_filter(
+        &root,
+        &[
+            PathBuf::from("src/**/*.rs"),
+            PathBuf::from("assets/*.css"),
+            PathBuf::from("Cargo.toml"),
+        ],
+    );
+
+    assert!(filter
+        .matched(root.join("src/main.rs"), false)
+        .is_whitelist());
+    assert!(filter
+        .matched(root.join("src/a/b/c.rs"), false)
+        .is_whitelist());
+    assert!(filter
+        .matched(root.join("assets/main.css"), false)
+        .is_whitelist());
+    assert!(filter
+        .matched(root.join("Cargo.toml"), false)
+        .is_whitelist());
+
+    assert!(filter
+        .matched(root.join("src/data.json"), false)
+        .is_ignore());
+    assert!(filter
+        .matched(root.join("assets/logo.png"), false)
+        .is_ignore());
+    assert!(filter.matched(root.join("README.md"), false).is_ignore());
+
+    // `**` at the root matches at any depth
+    let filter = build_watch_filter(&root, &[PathBuf::from("**/*.rs")]);
+    assert!(filter.matched(root.join("main.rs"), false).is_whitelist());
+    assert!(filter
+        .matched(root.join("examples/demo/main.rs"), false)
+        .is_whitelist());
+    assert!(filter
+        .matched(root.join("examples/demo/Cargo.toml"), false)
+        .is_ignore());
+}
+
+#[test]
+fn test_exclude_path_globs() {
+    let root = PathBuf::from("/project");
+    let ignore = build_ignore(
+        &root,
+        &root.join("dist"),
+        &WebWatcherConfig {
+            exclude_path: vec!["src/generated/".to_string(), "**/*.log".to_string()],
+            ..Default::default()
+        },
+    );
+
+    // `./target` stays excluded by default
+    assert!(ignore.matched(root.join("target"), true).is_ignore());
+
+    // A trailing slash only matches directories, not files with the same name
+    assert!(ignore.matched(root.join("src/generated"), true).is_ignore());
+    assert!(!ignore
+        .matched(root.join("src/generated"), false)
+        .is_ignore());
+
+    // `**` at the root matches files at any depth
+    assert!(ignore.matched(root.join("debug.log"), false).is_ignore());
+    assert!(ignore
+        .matched(root.join("src/nested/debug.log"), false)
+        .is_ignore());
+    assert!(!ignore.matched(root.join("src/main.rs"), false).is_ignore());
+}
+
+#[test]
+fn test_poll_watcher_delivers_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let mut watcher = create_watcher(
+        move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                _ = tx.send(event);
+            }
+        },
+        true,
+        Duration::from_millis(50),
+    )
+    .unwrap();
+    watcher
+        .watch(dir.path(), notify::RecursiveMode::Recursive)
+        .unwrap();
+
+    let file = dir.path().join("main.rs");
+    fs::write(&file, "fn main() {}").unwrap();
+
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(event.paths.iter().any(|path| path.ends_with("main.rs")));
+}
+
+#[test]
+fn test_respect_gitignore() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join(".gitignore"), "sandbox/\n").unwrap();
+
+    let ignore = build_ignore(root, &root.join("dist"), &WebWatcherConfig::default());
+    assert!(ignore.matched(root.join("sandbox"), true).is_ignore());
+
+    let ignore = build_ignore(
+        root,
+        &root.join("dist"),
+        &WebWatcherConfig {
+            respect_gitignore: false,
+            ..Default::default()
+        },
+    );
+    assert!(!ignore.matched(root.join("sandbox"), true).is_ignore());
+
+    // The built-in excludes still apply
+    assert!(ignore.matched(root.join("target"), true).is_ignore());
+}
+
+#[test]
+fn test_dioxusignore_precedence() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join(".gitignore"), "assets/generated.css\n").unwrap();
+    fs::write(
+        root.join(DIOXUS_IGNORE),
+        "!assets/generated.css\nbenches/\n!vendor/ui/\n",
+    )
+    .unwrap();
+
+    let ignore = build_ignore(
+        root,
+        &root.join("dist"),
+        &WebWatcherConfig {
+            exclude_path: vec!["vendor/".to_string(), "docs/".to_string()],
+            ..Default::default()
+        },
+    );
+
+    // .dioxusignore re-includes what .gitignore excluded
+    assert!(!ignore
+        .matched(root.join("assets/generated.css"), false)
+        .is_ignore());
+
+    // .dioxusignore excludes paths that git tracks
+    assert!(ignore.matched(root.join("benches"), true).is_ignore());
+
+    // .dioxusignore takes precedence over exclude_path, which still applies otherwise
+    assert!(ignore.matched(root.join("vendor"), true).is_ignore());
+    assert!(!ignore.matched(root.join("vendor/ui"), true).is_ignore());
+    assert!(ignore.matched(root.join("docs"), true).is_ignore());
+}
+
+#[test]
+fn test_exclude_resolution_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join(".gitignore"), "/target\nsandbox/\n").unwrap();
+
+    // The defaults apply on top of .gitignore and exclude_path adds to them rather than replacing them
+    let ignore = build_ignore(
+        root,
+        &root.join("dist"),
+        &WebWatcherConfig {
+            exclude_path: vec!["benches/".to_string()],
+            ..Default::default()
+        },
+    );
+    assert!(ignore.matched(root.join("node_modules"), true).is_ignore());
+    assert!(ignore.matched(root.join("sandbox"), true).is_ignore());
+    assert!(ignore.matched(root.join("benches"), true).is_ignore());
+
+    // Dropping the defaults doesn't drop .gitignore or exclude_path
+    let ignore = build_ignore(
+        root,
+        &root.join("dist"),
+        &WebWatcherConfig {
+            exclude_path: vec!["benches/".to_string()],
+            default_excludes: false,
+            ..Default::default()
+        },
+    );
+    assert!(!ignore.matched(root.join("node_modules"), true).is_ignore());
+    assert!(ignore.matched(root.join("target"), true).is_ignore());
+    assert!(ignore.matched(root.join("benches"), true).is_ignore());
+
+    // exclude_path is applied after .gitignore, so it can re-include what git ignores
+    let ignore = build_ignore(
+        root,
+        &root.join("dist"),
+        &WebWatcherConfig {
+            exclude_path: vec!["!/target".to_string()],
+            default_excludes: false,
+            ..Default::default()
+        },
+    );
+    assert!(!ignore.matched(root.join("target"), true).is_ignore());
+}
+
+#[test]
+fn test_asset_save_is_forwarded_once() {
+    use notify::event::DataChange;
+
+    let root = PathBuf::from("/project");
+    let css = root.join("assets/main.css");
+
+    // Editors often report a save as several events for the same file
+    let events = (0..3).map(|_| {
+        notify::Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path(css.clone())
+    });
+    let changed = ChangedFiles::sort(
+        changed_paths(events),
+        &root.join("assets"),
+        &[],
+        &HashMap::new(),
+    );
+
+    assert_eq!(
+        changed,
+        ChangedFiles {
+            assets: vec![css],
+            ..Default::default()
+        }
+    );
+
+    let changed = ChangedFiles::sort(
+        vec![
+            root.join("src/main.rs"),
+            root.join("style.css"),
+            root.join("README.md"),
+        ],
+        &root.join("assets"),
+        &[],
+        &HashMap::new(),
+    );
+    assert_eq!(changed.rust, vec![root.join("src/main.rs")]);
+    assert_eq!(changed.unknown, vec![root.join("README.md")]);
+
+    // Css is hot reloaded even outside the asset directory
+    assert_eq!(changed.assets, vec![root.join("style.css")]);
+}
+
+#[test]
+fn test_unchanged_templates_are_not_resent() {
+    let dir = tempfile::tempdir().unwrap();
+    let crate_dir = dir.path();
+    let file = crate_dir.join("src/main.rs");
+    let src = r#"use dioxus::prelude::*;
+
+// The title of the app
+fn title() -> Element {
+    rsx! { h1 { class: "title", "Counter" } }
+}
+
+fn body() -> Element {
+    rsx! { p { class: "body", "Count: 0" } }
+}
+
+fn app() -> Element {
+    rsx! { div { class: "app", "Hello" } }
+}
+"#;
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::write(&file, src).unwrap();
+
+    let mut file_map =
+        FileMap::create_with_filter::<HtmlCtx>(crate_dir.to_path_buf(), |_| false).unwrap();
+    file_map.in_workspace.insert(crate_dir.to_path_buf(), None);
+    let mut sent = HashMap::new();
+
+    // Editing an attribute sends the template it's in
+    let edited = src.replace(r#"class: "title""#, r#"class: "big-title""#);
+    fs::write(&file, &edited).unwrap();
+    let templates = file_map.update_rsx::<HtmlCtx>(&file, crate_dir).unwrap();
+    let first = unsent_templates(&mut sent, templates);
+    assert!(!first.is_empty());
+
+    // Editing outside of the rsx blocks doesn't send anything
+    fs::write(&file, edited.replace("The title", "The heading")).unwrap();
+    let templates = file_map.update_rsx::<HtmlCtx>(&file, crate_dir).unwrap();
+    assert!(unsent_templates(&mut sent, templates).is_empty());
+
+    // Neither does reporting the same templates again
+    assert!(unsent_templates(&mut sent, first.clone()).is_empty());
+
+    // Until we forget what was sent, like after a rebuild
+    sent.clear();
+    assert_eq!(
+        unsent_templates(&mut sent, first.clone()).len(),
+        first.len()
+    );
+}
+
+#[test]
+fn test_new_files_keep_the_rest_of_the_map() {
+    let dir = tempfile::tempdir().unwrap();
+    let crate_dir = dir.path();
+    let file = crate_dir.join("src/main.rs");
+    let src = r#"use dioxus::prelude::*;
+
+fn app() -> Element {
+    rsx! { div { class: "app", "Hello" } }
+}
+"#;
+    fs::create_dir_all(crate_dir.join("src/generated")).unwrap();
+    fs::write(&file, src).unwrap();
+    fs::write(crate_dir.join("src/generated/routes.rs"), src).unwrap();
+
+    let generated = crate_dir.join("src/generated");
+    let mut file_map =
+        FileMap::create_with_filter::<HtmlCtx>(crate_dir.to_path_buf(), |path| path == generated)
+            .unwrap();
+    file_map.in_workspace.insert(crate_dir.to_path_buf(), None);
+
+    // Hot reload an edit so main.rs has templates cached
+    fs::write(&file, src.replace("Hello", "Hi")).unwrap();
+    file_map.update_rsx::<HtmlCtx>(&file, crate_dir).unwrap();
+    let cached = file_map.map[&file].templates.clone();
+    assert!(!cached.is_empty());
+
+    // A new file forces a rebuild and is tracked from now on
+    let new_file = crate_dir.join("src/header.rs");
+    fs::write(&new_file, src).unwrap();
+    assert!(matches!(
+        file_map.update_rsx::<HtmlCtx>(&new_file, crate_dir),
+        Err(HotreloadError::Notreloadable)
+    ));
+    assert!(file_map.map.contains_key(&new_file));
+
+    // Without pulling in filtered files or dropping the templates of the files we already track
+    assert!(!file_map
+        .map
+        .contains_key(&crate_dir.join("src/generated/routes.rs")));
+    assert_eq!(file_map.map[&file].templates, cached);
+}
+
+#[test]
+fn test_diagnostic_from_syn() {
+    let src = "fn main() {\n    let x = ;\n}";
+    let err = syn::parse_file(src).unwrap_err();
+    let diagnostic = diagnostic_from_syn(&err, src);
+
+    assert_eq!(diagnostic.line, 2);
+    assert_eq!(diagnostic.column, 13);
+    assert_eq!(diagnostic.snippet, "let x = ;");
+    assert_eq!(diagnostic.category, DiagnosticCategory::ParseError);
+    assert!(!diagnostic.message.is_empty());
+}
+
+#[test]
+fn test_diagnostics_for_broken_rsx_fixture() {
+    let dir = tempfile::tempdir().unwrap();
+    let crate_dir = dir.path();
+    let file = crate_dir.join("src/main.rs");
+
+    // The fixture only breaks inside one rsx block, so the file itself parses fine
+    let broken = include_str!("../../tests/fixtures/broken_rsx.rs");
+    let fixed = broken.replace("        42\n", "        \"42\"\n");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::write(&file, &fixed).unwrap();
+
+    let mut file_map =
+        FileMap::create_with_filter::<HtmlCtx>(crate_dir.to_path_buf(), |_| false).unwrap();
+    file_map.in_workspace.insert(crate_dir.to_path_buf(), None);
+
+    // Break the first block and edit the second one in the same save
+    fs::write(&file, broken.replace("\"Counter\"", "\"Clicks\"")).unwrap();
+    let templates = file_map.update_rsx::<HtmlCtx>(&file, crate_dir).unwrap();
+
+    // The block that still parses is hot reloaded
+    assert!(!templates.is_empty());
+
+    // And the broken one is reported where it broke
+    let errors = &file_map.rsx_errors[&file];
+    assert_eq!(errors.len(), 1);
+    let diagnostic = diagnostic_from_syn(&errors[0], broken);
+    assert_eq!(diagnostic.line, 9);
+    assert_eq!(diagnostic.column, 9);
+    assert_eq!(diagnostic.snippet, "42");
+    assert_eq!(diagnostic.category, DiagnosticCategory::ParseError);
+
+    // Fixing the block clears its errors
+    fs::write(&file, fixed.replace("\"Counter\"", "\"Clicks\"")).unwrap();
+    file_map.update_rsx::<HtmlCtx>(&file, crate_dir).unwrap();
+    assert!(!file_map.rsx_errors.contains_key(&file));
+}
+
+#[test]
+fn test_diagnostics_for_unreadable_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut file_map =
+        FileMap::create_with_filter::<HtmlCtx>(dir.path().to_path_buf(), |_| false).unwrap();
+
+    let path = dir.path().join("main.rs");
+    fs::write(&path, [0xff, 0xfe, 0xfd]).unwrap();
+    let err = file_map
+        .update_rsx::<HtmlCtx>(&path, dir.path())
+        .unwrap_err();
+    assert!(matches!(err, HotreloadError::Failure(_)));
+
+    let diagnostics = diagnostic_from_error(&err, "");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (0, 0));
+    assert_eq!(diagnostics[0].snippet, "");
+    assert_eq!(diagnostics[0].category, DiagnosticCategory::IoError);
+}
+
+#[test]
+fn test_owning_crate_dir() {
+    let app = PathBuf::from("/workspace/app");
+    let members = vec![
+        PathBuf::from("/workspace/components"),
+        PathBuf::from("/workspace/components/icons"),
+    ];
+
+    assert_eq!(
+        owning_crate_dir(Path::new("/workspace/app/src/main.rs"), &app, &members),
+        app
+    );
+    assert_eq!(
+        owning_crate_dir(
+            Path::new("/workspace/components/src/lib.rs"),
+            &app,
+            &members
+        ),
+        members[0]
+    );
+    // Nested crates win over the crate they're nested in
+    assert_eq!(
+        owning_crate_dir(
+            Path::new("/workspace/components/icons/src/lib.rs"),
+            &app,
+            &members
+        ),
+        members[1]
+    );
+}
+
+#[test]
+fn test_files_under_deleted_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("src/components")).unwrap();
+    for file in [
+        "src/main.rs",
+        "src/components/nav.rs",
+        "src/components/footer.rs",
+        "src/components_old.rs",
+    ] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    let file_map = FileMap::create_with_filter::<HtmlCtx>(root.to_path_buf(), |_| false).unwrap();
+
+    // Only files inside the directory count, not siblings that share its name as a prefix
+    assert_eq!(
+        file_map.files_under(&root.join("src/components")),
+        vec![
+            root.join("src/components/footer.rs"),
+            root.join("src/components/nav.rs"),
+        ]
+    );
+    assert!(file_map.files_under(&root.join("assets")).is_empty());
+}
+
+#[test]
+fn test_save_via_rename() {
+    use notify::event::{CreateKind, RenameMode};
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("main.rs");
+    fs::write(&file, "fn main() {}").unwrap();
+    let file_map =
+        FileMap::create_with_filter::<HtmlCtx>(dir.path().to_path_buf(), |_| false).unwrap();
+
+    // Write the new contents to a temp file and rename it over the original, like vim and most IDEs do
+    let temp = dir.path().join("main.rs.tmp1234");
+    fs::write(&temp, "fn main() { println!(\"hi\") }").unwrap();
+    fs::rename(&temp, &file).unwrap();
+
+    let events = [
+        notify::Event::new(EventKind::Create(CreateKind::File)).add_path(temp.clone()),
+        notify::Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(temp.clone())
+            .add_path(file.clone()),
+    ];
+    let changed: Vec<PathBuf> = changed_paths(events)
+        .into_iter()
+        .filter(|path| !is_transient_file(path, &file_map))
+        .collect();
+    assert_eq!(changed, vec![file.clone()]);
+
+    // A tracked file that really was deleted still comes through
+    fs::remove_file(&file).unwrap();
+    assert!(!is_transient_file(&file, &file_map));
+}
+
+#[test]
+fn test_ignore_file_created_during_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let gitignore = root.join(".gitignore");
+    fs::create_dir_all(root.join("src")).unwrap();
+
+    assert!(is_ignore_file(&gitignore, root));
+    assert!(is_ignore_file(&root.join(DIOXUS_IGNORE), root));
+    assert!(is_ignore_file(&root.join("src/.gitignore"), root));
+    assert!(!is_ignore_file(&root.join("src/main.rs"), root));
+
+    let (tx, _rx) = futures_channel::mpsc::unbounded();
+    let mut watchers = FileWatchers::for_test(
+        create_watcher(
+            notify_event_handler(tx.clone()),
+            false,
+            Duration::from_millis(50),
+        )
+        .unwrap(),
+        tx,
+    );
+
+    // There's no .gitignore at startup, so it's watched once it shows up
+    let failed =
+        register_watch_paths(&mut watchers, &[gitignore.clone(), root.join("src")]).unwrap();
+    assert!(failed.is_empty());
+    assert!(watchers.has_missing());
+
+    let config = WebWatcherConfig::default();
+    let ignore = IgnoreRules::new(root, &root.join("dist"), &root.join("target"), &config);
+    assert!(!ignore
+        .matched(&root.join("src/scratch.rs"), false)
+        .is_ignore());
+
+    // Creating it comes through as a create event for an ignore file, which reloads the rules
+    fs::write(&gitignore, "src/scratch.rs\n").unwrap();
+    let events: Vec<notify::Event> = watchers
+        .refresh(&[])
+        .iter()
+        .flat_map(|root| created_events(root))
+        .collect();
+    assert!(events
+        .iter()
+        .all(|event| matches!(event.kind, EventKind::Create(_))));
+    let changed = changed_paths(events);
+    assert_eq!(changed, vec![gitignore.clone()]);
+    assert!(changed.iter().any(|path| is_ignore_file(path, root)));
+
+    let ignore = IgnoreRules::new(root, &root.join("dist"), &root.join("target"), &config);
+    assert!(ignore
+        .matched(&root.join("src/scratch.rs"), false)
+        .is_ignore());
+}
+
+#[test]
+fn test_nested_gitignore() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("examples/output")).unwrap();
+    fs::create_dir_all(root.join("examples/fixtures")).unwrap();
+    fs::create_dir_all(root.join("src/generated")).unwrap();
+    fs::write(root.join(".gitignore"), "target/\n").unwrap();
+    fs::write(root.join("examples/.gitignore"), "output/\n*.rs.bk\n").unwrap();
+    fs::write(root.join("examples/fixtures/.gitignore"), "!keep.rs.bk\n").unwrap();
+    fs::write(root.join("src/.gitignore"), "generated/\n").unwrap();
+
+    let ignore = IgnoreRules::new(
+        root,
+        &root.join("dist"),
+        &root.join("target"),
+        &WebWatcherConfig::default(),
+    );
+
+    // Nested rules apply beneath their own directory
+    assert!(ignore
+        .matched(&root.join("examples/output"), true)
+        .is_ignore());
+    assert!(ignore
+        .matched(&root.join("examples/main.rs.bk"), false)
+        .is_ignore());
+    assert!(ignore
+        .matched(&root.join("src/generated"), true)
+        .is_ignore());
+
+    // ...and only there, since patterns are relative to the file that declares them
+    assert!(!ignore.matched(&root.join("output"), true).is_ignore());
+    assert!(!ignore.matched(&root.join("main.rs.bk"), false).is_ignore());
+
+    // The deepest matching file wins
+    assert!(ignore
+        .matched(&root.join("examples/fixtures/keep.rs.bk"), false)
+        .is_whitelist());
+    assert!(ignore
+        .matched(&root.join("examples/fixtures/other.rs.bk"), false)
+        .is_ignore());
+
+    // Turning off respect_gitignore turns off the nested files too
+    let ignore = IgnoreRules::new(
+        root,
+        &root.join("dist"),
+        &root.join("target"),
+        &WebWatcherConfig {
+            respect_gitignore: false,
+            ..Default::default()
+        },
+    );
+    assert!(!ignore
+        .matched(&root.join("examples/output"), true)
+        .is_ignore());
+}
+
+#[test]
+fn test_manifest_changes() {
+    let manifest = |src: &str| toml::from_str::<toml::Table>(src).unwrap();
+    let old = manifest(
+        r#"
+        [package]
+        name = "app"
+        description = "An app"
+
+        [dependencies]
+        dioxus = "0.5"
+        serde = "1.0.100"
+        "#,
+    );
+
+    // Metadata and comments don't affect the build
+    let new = manifest(
+        r#"
+        # A comment
+        [package]
+        name = "app"
+        description = "A better app"
+
+        [dependencies]
+        dioxus = "0.5"
+        serde = "1.0.100"
+        "#,
+    );
+    assert!(manifest_changes(&old, &new).is_empty());
+
+    let new = manifest(
+        r#"
+        [package]
+        name = "app"
+
+        [dependencies]
+        dioxus = "0.5"
+        serde = "1.0.200"
+        tokio = "1"
+
+        [features]
+        default = []
+        "#,
+    );
+    assert_eq!(
+        manifest_changes(&old, &new),
+        vec![
+            "dependency `serde` changed".to_string(),
+            "dependency `tokio` was added".to_string(),
+            "`[features]` changed".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_content_hashes_skip_noop_saves() {
+    let dir = tempfile::tempdir().unwrap();
+    let css = dir.path().join("main.css");
+    let rs = dir.path().join("main.rs");
+    fs::write(&css, "body { color: red; }").unwrap();
+    fs::write(&rs, "fn main() {}").unwrap();
+
+    let mut hashes = ContentHashes::default();
+
+    // We have nothing to compare the first event against
+    assert!(hashes.changed(&css, None));
+
+    // Touching the file or rewriting the same bytes isn't a change
+    fs::File::options()
+        .append(true)
+        .open(&css)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now())
+        .unwrap();
+    assert!(!hashes.changed(&css, None));
+    fs::write(&css, "body { color: red; }").unwrap();
+    assert!(!hashes.changed(&css, None));
+
+    fs::write(&css, "body { color: blue; }").unwrap();
+    assert!(hashes.changed(&css, None));
+
+    // Files the FileMap already parsed are compared against that source on their first event
+    assert!(!hashes.changed(&rs, Some("fn main() {}")));
+    fs::write(&rs, "fn main() { todo!() }").unwrap();
+    assert!(hashes.changed(&rs, None));
+
+    fs::remove_file(&css).unwrap();
+    assert!(hashes.changed(&css, None));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_event_storms_are_collected_into_one_batch() {
+    use notify::event::DataChange;
+
+    let (tx, mut rx) = futures_channel::mpsc::unbounded();
+    let edit = |path: String| {
+        notify::Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path(PathBuf::from(path))
+    };
+
+    // A checkout delivers two bursts, with a gap longer than the normal debounce window between them
+    for i in 0..20 {
+        tx.unbounded_send(edit(format!("src/first_{i}.rs")))
+            .unwrap();
+    }
+    let second_burst = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        for i in 0..20 {
+            tx.unbounded_send(edit(format!("src/second_{i}.rs")))
+                .unwrap();
+        }
+        tx
+    });
+
+    let mut queue = vec![];
+    debounce_events(
+        &mut rx,
+        &mut queue,
+        Duration::from_millis(20),
+        far_deadline(),
+    )
+    .await;
+    assert_eq!(queue.len(), 20);
+
+    assert!(is_event_storm(&queue, 10));
+    debounce_events(
+        &mut rx,
+        &mut queue,
+        Duration::from_millis(300),
+        far_deadline(),
+    )
+    .await;
+    drop(second_burst.await.unwrap());
+    assert_eq!(changed_paths(queue).len(), 40);
+
+    // Repeated saves of a handful of files are not a storm
+    let saves: Vec<_> = (0..30).map(|_| edit("src/main.rs".to_string())).collect();
+    assert!(!is_event_storm(&saves, 10));
+}
+
+#[test]
+fn test_extension_policy() {
+    use ExtensionPolicy::*;
+
+    let root = PathBuf::from("/project");
+    let asset_dir = root.join("assets");
+    let policies = HashMap::from([
+        ("md".to_string(), Rebuild),
+        ("log".to_string(), Ignore),
+        ("css".to_string(), Rebuild),
+        ("rsx".to_string(), Rsx),
+    ]);
+
+    let cases = [
+        // (path, defaults, with the user's policies)
+        ("src/main.rs", Some(Rsx), Some(Rsx)),
+        ("src/style.css", Some(Asset), Some(Rebuild)),
+        ("assets/logo.png", Some(Asset), Some(Asset)),
+        ("assets/notes.md", Some(Asset), Some(Rebuild)),
+        ("README.md", None, Some(Rebuild)),
+        ("debug.log", None, Some(Ignore)),
+        ("src/view.rsx", None, Some(Rsx)),
+        ("data.json", None, None),
+    ];
+
+    for (path, default, configured) in cases {
+        let path = root.join(path);
+        let ext = path.extension().unwrap().to_str().unwrap();
+        assert_eq!(
+            extension_policy(ext, &path, &asset_dir, &HashMap::new()),
+            default,
+            "default policy for {path:?}"
+        );
+        assert_eq!(
+            extension_policy(ext, &path, &asset_dir, &policies),
+            configured,
+            "configured policy for {path:?}"
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinked_crate_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let real = dir.path().join("real");
+    let link = dir.path().join("link");
+    fs::create_dir_all(real.join("src/shared")).unwrap();
+    fs::create_dir_all(real.join("target/debug")).unwrap();
+    fs::write(real.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(real.join("src/shared/lib.rs"), "").unwrap();
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+    // A symlinked source directory inside the project reaches the same files under a second name
+    std::os::unix::fs::symlink(real.join("src/shared"), real.join("src/alias")).unwrap();
+
+    // Cargo reports the crate through the symlink, notify reports the canonical paths
+    let canonical = real.canonicalize().unwrap();
+    let aliases = PathAliases::new([&link]);
+    let changed = aliases.normalize(vec![
+        canonical.join("src/main.rs"),
+        canonical.join("target/debug/build/out.rs"),
+        canonical.join("src/shared/lib.rs"),
+        canonical.join("src/alias/lib.rs"),
+    ]);
+    assert_eq!(
+        changed,
+        vec![
+            link.join("src/main.rs"),
+            link.join("target/debug/build/out.rs"),
+            link.join("src/shared/lib.rs"),
+        ]
+    );
+
+    // Once normalized, the default excludes and the out dir match again
+    let ignore = build_ignore(&link, &canonical.join("dist"), &WebWatcherConfig::default());
+    assert!(ignore
+        .matched_path_or_any_parents(&changed[1], false)
+        .is_ignore());
+    assert!(ignore
+        .matched_path_or_any_parents(link.join("dist/index.html"), false)
+        .is_ignore());
+    assert!(!ignore
+        .matched_path_or_any_parents(&changed[0], false)
+        .is_ignore());
+
+    // Paths that are already in cargo's form are left alone
+    assert_eq!(
+        aliases.normalize(vec![link.join("src/main.rs")]),
+        vec![link.join("src/main.rs")]
+    );
+}
+
+#[tokio::test]
+async fn test_watch_limit_falls_back_to_polling() {
+    /// A native watcher that has run out of inotify watches
+    struct ExhaustedWatcher;
+
+    impl notify::Watcher for ExhaustedWatcher {
+        fn new<F: notify::EventHandler>(_: F, _: Config) -> notify::Result<Self> {
+            Ok(Self)
+        }
+
+        fn watch(&mut self, _: &Path, _: notify::RecursiveMode) -> notify::Result<()> {
+            Err(notify::Error::new(notify::ErrorKind::MaxFilesWatch))
+        }
+
+        fn unwatch(&mut self, _: &Path) -> notify::Result<()> {
+            Ok(())
+        }
+
+        fn kind() -> notify::WatcherKind {
+            notify::WatcherKind::Inotify
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, mut rx) = futures_channel::mpsc::unbounded();
+    let mut watchers = FileWatchers::for_test(Box::new(ExhaustedWatcher), tx);
+
+    // The path still gets watched, just by the poll watcher
+    watchers.watch(dir.path()).unwrap();
+    assert!(watchers.poll.is_some());
+
+    fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+    let event = tokio::time::timeout(Duration::from_secs(5), rx.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(event.paths.iter().any(|path| path.ends_with("main.rs")));
+}
+
+#[test]
+fn test_is_watch_limit_error() {
+    assert!(is_watch_limit_error(&notify::Error::new(
+        notify::ErrorKind::MaxFilesWatch
+    )));
+    #[cfg(target_os = "linux")]
+    assert!(is_watch_limit_error(&notify::Error::io(
+        std::io::Error::from_raw_os_error(28)
+    )));
+    assert!(!is_watch_limit_error(&notify::Error::path_not_found()));
+}
+
+#[tokio::test]
+async fn test_watch_paths_created_after_startup() {
+    let dir = tempfile::tempdir().unwrap();
+    let components = dir.path().join("components");
+    let (tx, mut rx) = futures_channel::mpsc::unbounded();
+    let mut watchers = FileWatchers::for_test(
+        create_watcher(
+            notify_event_handler(tx.clone()),
+            false,
+            Duration::from_millis(50),
+        )
+        .unwrap(),
+        tx,
+    );
+
+    async fn next_change(rx: &mut UnboundedReceiver<notify::Event>) -> notify::Event {
+        tokio::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    // The directory doesn't exist yet, so it's remembered for later
+    let err = watchers.watch(&components).unwrap_err();
+    assert!(is_not_found_error(&err));
+    assert!(watchers.has_missing());
+    assert!(watchers.refresh(&[]).is_empty());
+
+    // Once it's created it gets watched, and edits in it come through
+    fs::create_dir(&components).unwrap();
+    assert_eq!(watchers.refresh(&[]), vec![components.clone()]);
+    assert!(!watchers.has_missing());
+    fs::write(components.join("button.rs"), "fn main() {}").unwrap();
+    let event = next_change(&mut rx).await;
+    assert!(event.paths.iter().any(|path| path.ends_with("button.rs")));
+
+    // Deleting and recreating the directory registers it again
+    fs::remove_dir_all(&components).unwrap();
+    assert!(watchers
+        .refresh(std::slice::from_ref(&components))
+        .is_empty());
+    assert!(watchers.has_missing());
+    fs::create_dir(&components).unwrap();
+    assert_eq!(watchers.refresh(&[]), vec![components.clone()]);
+    while rx.try_next().is_ok() {}
+    fs::write(components.join("card.rs"), "fn main() {}").unwrap();
+    loop {
+        let event = next_change(&mut rx).await;
+        if event.paths.iter().any(|path| path.ends_with("card.rs")) {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_notify_errors_request_a_rescan() {
+    let (tx, mut rx) = futures_channel::mpsc::unbounded();
+    let mut handler = notify_event_handler(tx);
+
+    handler(Err(notify::Error::generic("inotify queue overflowed")));
+    handler(Ok(
+        notify::Event::new(EventKind::Other).set_flag(notify::event::Flag::Rescan)
+    ));
+    handler(Ok(notify::Event::new(EventKind::Access(
+        notify::event::AccessKind::Any,
+    ))));
+
+    let error = rx.try_next().unwrap().unwrap();
+    assert!(error.need_rescan());
+    assert_eq!(error.info(), Some(WATCHER_ERROR));
+    let rescan = rx.try_next().unwrap().unwrap();
+    assert!(rescan.need_rescan());
+    assert_eq!(rescan.info(), None);
+    // Events we don't care about are still dropped
+    assert!(rx.try_next().is_err());
+}
+
+#[test]
+fn test_changed_since_last_seen() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["unchanged.rs", "edited.rs", "deleted.rs"] {
+        fs::write(dir.path().join(name), "fn main() {}").unwrap();
+    }
+    let mut file_map =
+        FileMap::create_with_filter::<HtmlCtx>(dir.path().to_path_buf(), |_| false).unwrap();
+    let style = dir.path().join("style.css");
+    fs::write(&style, "body {}").unwrap();
+    let mut content_hashes = ContentHashes::default();
+    assert!(content_hashes.changed(&style, None));
+
+    // These changes happen while notify is dropping events
+    fs::write(dir.path().join("edited.rs"), "fn main() { todo!() }").unwrap();
+    fs::remove_file(dir.path().join("deleted.rs")).unwrap();
+    fs::write(&style, "body { color: red; }").unwrap();
+
+    assert_eq!(
+        changed_since_last_seen(&file_map, &mut content_hashes),
+        (
+            4,
+            vec![
+                dir.path().join("deleted.rs"),
+                dir.path().join("edited.rs"),
+                style.clone(),
+            ]
+        )
+    );
+    // Once we've seen the changes (and the hot reload stopped tracking the deleted file), they aren't reported again
+    file_map.map.remove(&dir.path().join("deleted.rs"));
+    assert_eq!(
+        changed_since_last_seen(&file_map, &mut content_hashes),
+        (3, vec![])
+    );
+}
+
+#[test]
+fn test_strip_path_prefix() {
+    let root = Path::new("/Users/me/proj");
+
+    assert_eq!(
+        strip_path_prefix(Path::new("/users/ME/proj/src/main.rs"), root, true),
+        Some(Path::new("src/main.rs"))
+    );
+    assert_eq!(
+        strip_path_prefix(Path::new("/Users/me/proj/src/main.rs"), root, false),
+        Some(Path::new("src/main.rs"))
+    );
+    // Case-sensitive filesystems keep treating differently cased paths as different paths
+    assert_eq!(
+        strip_path_prefix(Path::new("/users/ME/proj/src/main.rs"), root, false),
+        None
+    );
+    // Only whole components match
+    assert_eq!(
+        strip_path_prefix(Path::new("/Users/me/project/main.rs"), root, true),
+        None
+    );
+    assert_eq!(strip_path_prefix(Path::new("/Users/me"), root, true), None);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_excludes_ignore_case_on_windows() {
+    let root = PathBuf::from(r"C:\Users\me\proj");
+    let aliases = PathAliases::new([&root]);
+
+    // The event spells both the crate dir and the target dir differently than we do
+    let path = aliases.alias(PathBuf::from(r"c:\users\ME\proj\Target\debug\build.rs"));
+    assert_eq!(path, root.join(r"Target\debug\build.rs"));
+
+    let ignore = build_ignore(&root, &root.join("dist"), &WebWatcherConfig::default());
+    assert!(ignore.matched(&path, false).is_ignore());
+    assert!(ignore
+        .matched(root.join(r"DIST\index.html"), false)
+        .is_ignore());
+    assert!(!ignore.matched(root.join(r"src\main.rs"), false).is_ignore());
+
+    let filter = build_watch_filter(&root, &[PathBuf::from("src")]);
+    assert!(!filter.matched(root.join(r"SRC\main.rs"), false).is_ignore());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_wait_for_settle_reads_the_finished_file() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("main.rs");
+    let contents = "fn app() -> Element { rsx! { div { \"hello\" } } }\n".repeat(20);
+
+    // Write the file a chunk at a time, faster than we check on it, like a big save over a slow disk
+    let mut handle = fs::File::create(&file).unwrap();
+    let writer = tokio::spawn({
+        let contents = contents.clone();
+        async move {
+            for chunk in contents.as_bytes().chunks(64) {
+                handle.write_all(chunk).unwrap();
+                handle.flush().unwrap();
+                tokio::time::sleep(SETTLE_CHECK_INTERVAL / 3).await;
+            }
+        }
+    });
+
+    tokio::task::yield_now().await;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    wait_for_settle(&file, deadline).await;
+    assert!(writer.is_finished());
+    let settled = fs::read_to_string(&file).unwrap();
+    assert_eq!(settled, contents);
+    assert!(syn::parse_file(&settled).is_ok());
+
+    // A file that never stops changing is read anyway once the deadline passes
+    let mut handle = fs::OpenOptions::new().append(true).open(&file).unwrap();
+    let writer = tokio::spawn(async move {
+        loop {
+            handle.write_all(b"// still typing\n").unwrap();
+            tokio::time::sleep(SETTLE_CHECK_INTERVAL / 3).await;
+        }
+    });
+
+    tokio::task::yield_now().await;
+    let start = tokio::time::Instant::now();
+    wait_for_settle(&file, start + Duration::from_millis(100)).await;
+    assert_eq!(start.elapsed(), Duration::from_millis(100));
+    writer.abort();
+}
+
+#[test]
+fn test_lockfile_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let workspace = dir.path();
+
+    // Cargo rewrites the lockfile without changing it all the time, which shouldn't rebuild
+    let lockfile = workspace.join("Cargo.lock");
+    fs::write(&lockfile, "version = 3\n").unwrap();
+    let mut content_hashes = ContentHashes::default();
+    assert!(content_hashes.changed(&lockfile, None));
+    fs::write(&lockfile, "version = 3\n").unwrap();
+    assert!(!content_hashes.changed(&lockfile, None));
+    fs::write(&lockfile, "version = 4\n").unwrap();
+    assert!(content_hashes.changed(&lockfile, None));
+}
+
+#[test]
+fn test_target_dir_outside_crate_is_excluded() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = &dir.path().join("app");
+    fs::create_dir_all(root.join("src")).unwrap();
+
+    // Like CARGO_TARGET_DIR=/somewhere/else
+    let target_dir = dir.path().join("cache/cargo-target");
+    let ignore = IgnoreRules::new(
+        root,
+        &root.join("dist"),
+        &target_dir,
+        &WebWatcherConfig::default(),
+    );
+
+    // Build script output is generated rust code, but not source we want to hot reload
+    assert!(ignore.matched(&target_dir, true).is_ignore());
+    assert!(ignore
+        .matched(
+            &target_dir.join("debug/build/app-1234/out/generated.rs"),
+            false
+        )
+        .is_ignore());
+    assert!(!ignore.matched(&root.join("src/main.rs"), false).is_ignore());
+    assert!(!ignore
+        .matched(&dir.path().join("cache/cargo-target-old/lib.rs"), false)
+        .is_ignore());
+
+    // A target dir inside the crate with a custom name is excluded too, so the file map skips it
+    let custom = root.join("build-output");
+    let ignore = IgnoreRules::new(
+        root,
+        &root.join("dist"),
+        &custom,
+        &WebWatcherConfig::default(),
+    );
+    assert!(ignore.matched(&custom, true).is_ignore());
+    assert!(ignore
+        .matched(&custom.join("debug/deps/app.rs"), false)
+        .is_ignore());
+
+    // Unless the user turned the default excludes off
+    let ignore = IgnoreRules::new(
+        root,
+        &root.join("dist"),
+        &custom,
+        &WebWatcherConfig {
+            default_excludes: false,
+            ..Default::default()
+        },
+    );
+    assert!(!ignore
+        .matched(&custom.join("debug/deps/app.rs"), false)
+        .is_ignore());
+}
+
+#[test]
+fn test_asset_dirs() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let static_dir = root.join("static");
+    fs::create_dir_all(static_dir.join("fonts")).unwrap();
+
+    // Files in the asset dirs are assets whatever their extension, even rust files and files without one
+    let font = static_dir.join("fonts/Inter");
+    let generated = static_dir.join("generated.rs");
+    let changed = ChangedFiles::sort(
+        vec![font.clone(), generated.clone(), root.join("src/main.rs")],
+        &root.join("assets"),
+        std::slice::from_ref(&static_dir),
+        &HashMap::from([("rs".to_string(), ExtensionPolicy::Rebuild)]),
+    );
+    assert_eq!(changed.assets, vec![font.clone(), generated]);
+    assert_eq!(changed.rebuild, vec![root.join("src/main.rs")]);
+
+    // Binary assets are hashed as bytes, and the path is relative to the asset dir
+    let bytes = [0u8, 159, 146, 150, 255];
+    fs::write(&font, bytes).unwrap();
+    let roots = [static_dir.as_path(), root];
+    let details = asset_details(&font, &roots, 1024);
+    assert_eq!(details.path, PathBuf::from("fonts/Inter"));
+    assert_eq!(details.size, 5);
+    assert_eq!(
+        details.hash,
+        Some(format!("{:016x}", hash_contents(&bytes)))
+    );
+
+    // Large assets aren't hashed, and deleted ones have nothing to hash
+    assert_eq!(asset_details(&font, &roots, 4).hash, None);
+    fs::remove_file(&font).unwrap();
+    let details = asset_details(&font, &roots, 1024);
+    assert_eq!((details.size, details.hash), (0, None));
+}
+
+#[test]
+fn test_duplicate_events_collapse() {
+    use notify::event::{DataChange, RemoveKind};
+
+    let file = PathBuf::from("/project/src/main.rs");
+    let other = PathBuf::from("/project/src/lib.rs");
+    let modify = |kind: ModifyKind, path: &PathBuf| {
+        notify::Event::new(EventKind::Modify(kind)).add_path(path.clone())
+    };
+
+    // One save, reported as three events
+    let mut queue = vec![];
+    queue_event(
+        &mut queue,
+        modify(ModifyKind::Metadata(MetadataKind::WriteTime), &file),
+    );
+    queue_event(
+        &mut queue,
+        modify(ModifyKind::Data(DataChange::Content), &file),
+    );
+    queue_event(&mut queue, modify(ModifyKind::Any, &file));
+    assert_eq!(queue.len(), 1);
+
+    // Other files, and events that aren't modifications, are kept
+    queue_event(&mut queue, modify(ModifyKind::Any, &other));
+    queue_event(
+        &mut queue,
+        notify::Event::new(EventKind::Remove(RemoveKind::File)).add_path(other.clone()),
+    );
+    queue_event(&mut queue, modify(ModifyKind::Any, &file));
+    assert_eq!(queue.len(), 4);
+
+    // The file is only parsed once for the whole batch
+    let changed = ChangedFiles::sort(
+        changed_paths(queue),
+        Path::new("/project/assets"),
+        &[],
+        &HashMap::new(),
+    );
+    assert_eq!(changed.rust, vec![file, other]);
+}
+
+#[test]
+fn test_interleaved_events_are_not_collapsed() {
+    use notify::event::{CreateKind, RemoveKind};
+
+    let file = PathBuf::from("/project/src/main.rs");
+    let other = PathBuf::from("/project/src/lib.rs");
+    let modify = |path: &PathBuf| {
+        notify::Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.clone())
+    };
+
+    // Modifications of different paths are kept apart
+    let mut queue = vec![];
+    queue_event(&mut queue, modify(&file));
+    queue_event(&mut queue, modify(&other));
+    queue_event(&mut queue, modify(&file));
+    assert_eq!(queue.len(), 3);
+
+    // A file that is removed and created again between two saves keeps every event
+    let mut queue = vec![];
+    queue_event(&mut queue, modify(&file));
+    queue_event(
+        &mut queue,
+        notify::Event::new(EventKind::Remove(RemoveKind::File)).add_path(file.clone()),
+    );
+    queue_event(
+        &mut queue,
+        notify::Event::new(EventKind::Create(CreateKind::File)).add_path(file.clone()),
+    );
+    queue_event(&mut queue, modify(&file));
+    assert_eq!(queue.len(), 4);
+}
+
+#[test]
+fn test_renames_are_not_collapsed() {
+    use notify::event::{DataChange, RenameMode};
+
+    let file = PathBuf::from("/project/src/main.rs");
+
+    // A write followed by a rename of the same path keeps the rename
+    let mut queue = vec![];
+    queue_event(
+        &mut queue,
+        notify::Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path(file.clone()),
+    );
+    queue_event(
+        &mut queue,
+        notify::Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
+            .add_path(file.clone()),
+    );
+    assert_eq!(queue.len(), 2);
+    assert!(matches!(
+        queue[1].kind,
+        EventKind::Modify(ModifyKind::Name(RenameMode::From))
+    ));
+}
+
+#[test]
+fn test_watch_path_failures() {
+    /// A watcher that can't watch anything called `forbidden`
+    struct PickyWatcher;
+
+    impl notify::Watcher for PickyWatcher {
+        fn new<F: notify::EventHandler>(_: F, _: Config) -> notify::Result<Self> {
+            Ok(Self)
+        }
+
+        fn watch(&mut self, path: &Path, _: notify::RecursiveMode) -> notify::Result<()> {
+            match path.ends_with("forbidden") {
+                true => Err(notify::Error::generic("permission denied")),
+                false => Ok(()),
+            }
+        }
+
+        fn unwatch(&mut self, _: &Path) -> notify::Result<()> {
+            Ok(())
+        }
+
+        fn kind() -> notify::WatcherKind {
+            notify::WatcherKind::Inotify
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    let forbidden = dir.path().join("forbidden");
+    fs::create_dir_all(&src).unwrap();
+    fs::create_dir_all(&forbidden).unwrap();
+    let (tx, _rx) = futures_channel::mpsc::unbounded();
+    let mut watchers = FileWatchers::for_test(Box::new(PickyWatcher), tx);
+
+    // One bad path among good ones is reported, but we keep going. Missing paths are retried later instead
+    let components = dir.path().join("components");
+    let failed = register_watch_paths(
+        &mut watchers,
+        &[src.clone(), forbidden.clone(), components.clone()],
+    )
+    .unwrap();
+    assert_eq!(
+        failed,
+        vec![(forbidden.clone(), "permission denied".to_string())]
+    );
+    assert_eq!(watchers.roots, vec![src]);
+    assert_eq!(watchers.missing, vec![components]);
+
+    // If nothing can be watched, hot reloading can't work
+    let err = register_watch_paths(&mut watchers, std::slice::from_ref(&forbidden)).unwrap_err();
+    assert!(err.to_string().contains("forbidden"));
 }
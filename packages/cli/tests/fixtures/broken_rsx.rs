@@ -0,0 +1,17 @@
+use dioxus::prelude::*;
+
+fn app() -> Element {
+    let count = use_signal(|| 0);
+
+    rsx! {
+        h1 { "High-Five counter: {count}" }
+        button { onclick: move |_| count += 1, "Up high!" }
+        42
+    }
+}
+
+fn header() -> Element {
+    rsx! {
+        header { "Counter" }
+    }
+}
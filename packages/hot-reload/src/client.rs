@@ -1,5 +1,5 @@
 use crate::HotReloadMsg;
-use dioxus_core::{ScopeId, VirtualDom};
+use dioxus_core::{internal::HotReloadedTemplate, ScopeId, VirtualDom};
 use dioxus_signals::Writable;
 use warnings::Warning;
 
@@ -21,5 +21,16 @@ pub fn apply_changes(dom: &mut VirtualDom, msg: &HotReloadMsg) {
                 });
             }
         }
+
+        // Templates that no longer exist revert to the version that was compiled into the app
+        for id in &msg.cleared_templates {
+            if let Some(mut signal) = ctx.get_signal_with_key::<Option<HotReloadedTemplate>>(id) {
+                dioxus_signals::warnings::signal_read_and_write_in_reactive_scope::allow(|| {
+                    dioxus_signals::warnings::signal_write_in_component_body::allow(|| {
+                        signal.set(None);
+                    });
+                });
+            }
+        }
     });
 }
@@ -14,6 +14,76 @@ mod ws_receiver;
 #[cfg(feature = "serve")]
 pub use ws_receiver::*;
 
+/// The version of the messages exchanged between the devserver and its clients.
+///
+/// Bump this whenever [`DevserverMsg`] or [`HotReloadMsg`] change in a way older clients can't parse.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The version of this crate. The devserver and its clients are often built from different versions.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The header the devserver uses to advertise its [`PROTOCOL_VERSION`] when a client connects
+pub const PROTOCOL_VERSION_HEADER: &str = "x-dioxus-protocol-version";
+
+/// The header the devserver uses to advertise its [`VERSION`] when a client connects
+pub const VERSION_HEADER: &str = "x-dioxus-version";
+
+/// How one end of a hot reload connection should treat the other, based on the versions it advertised
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionCheck {
+    /// The versions match, or the other end is too old to tell us its version
+    Compatible,
+
+    /// The protocol matches but the crate versions differ. Hot reloading still works.
+    VersionMismatch { peer: String },
+
+    /// The other end speaks a protocol we can't parse. Clients should stop reading devserver messages.
+    ProtocolMismatch { peer: String },
+}
+
+impl VersionCheck {
+    /// Compare the protocol and crate versions the other end advertised against our own
+    pub fn new(protocol: Option<&str>, version: Option<&str>) -> Self {
+        let Some(protocol) = protocol else {
+            return Self::Compatible;
+        };
+
+        let peer = version
+            .map(|version| version.to_string())
+            .unwrap_or_else(|| format!("protocol {protocol}"));
+
+        if protocol.trim().parse::<u32>().ok() != Some(PROTOCOL_VERSION) {
+            return Self::ProtocolMismatch { peer };
+        }
+
+        match version {
+            Some(version) if version != VERSION => Self::VersionMismatch { peer },
+            _ => Self::Compatible,
+        }
+    }
+
+    /// Whether the client should keep reading messages from the devserver
+    pub fn is_compatible(&self) -> bool {
+        !matches!(self, Self::ProtocolMismatch { .. })
+    }
+}
+
+impl std::fmt::Display for VersionCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Compatible => write!(f, "hot reload versions match"),
+            Self::VersionMismatch { peer } => write!(
+                f,
+                "hot reload version mismatch (client {VERSION}, server {peer}). Hot reloading should still work, but consider installing a matching version of the dioxus CLI"
+            ),
+            Self::ProtocolMismatch { peer } => write!(
+                f,
+                "hot reload disabled: version mismatch (client {VERSION}, server {peer})"
+            ),
+        }
+    }
+}
+
 /// A message the hot reloading server sends to the client
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum DevserverMsg {
@@ -30,6 +100,15 @@ pub enum DevserverMsg {
     /// The app should reload completely if it can
     FullReloadCommand,
 
+    /// The rsx in a file failed to parse, or a file that previously failed to parse was fixed.
+    ///
+    /// Renderers can use this to show an error overlay. An empty list of errors means the overlay for
+    /// this file should be cleared.
+    Diagnostics {
+        file: PathBuf,
+        errors: Vec<HotReloadDiagnostic>,
+    },
+
     /// The program is shutting down completely - maybe toss up a splash screen or something?
     Shutdown,
 }
@@ -43,6 +122,16 @@ pub enum ClientMsg {
         level: String,
         messages: Vec<String>,
     },
+
+    /// Ask the devserver to check every file for changes it may have missed and hot reload them
+    RequestRescan,
+
+    /// Ask the devserver to stop hot reloading until it gets a [`ClientMsg::Resume`]. Changes made in the
+    /// meantime are handled in one batch on resume.
+    Pause,
+
+    /// Ask the devserver to resume hot reloading after a [`ClientMsg::Pause`]
+    Resume,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -52,6 +141,60 @@ pub struct HotReloadMsg {
 
     /// A file changed that's not an asset or a rust file - best of luck!
     pub unknown_files: Vec<PathBuf>,
+
+    /// The locations of hot reloaded templates that no longer exist, e.g. because their file was deleted.
+    /// Clients should drop the hot reloaded version and fall back to the compiled template.
+    #[serde(default)]
+    pub cleared_templates: Vec<String>,
+
+    /// Details about the changed assets, so renderers can tell which cached files need to be refreshed
+    #[serde(default)]
+    pub asset_details: Vec<HotReloadAsset>,
+}
+
+/// A changed asset. The devserver doesn't send the contents - desktop renderers can read the file, and web
+/// renderers can fetch it from the devserver.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HotReloadAsset {
+    /// The path of the asset, relative to the asset directory it lives in
+    pub path: PathBuf,
+
+    /// The size of the asset in bytes, or 0 if it was deleted
+    pub size: u64,
+
+    /// A hash of the contents, as hex. This is missing for deleted assets and large assets we don't hash.
+    pub hash: Option<String>,
+}
+
+/// An error the devserver ran into while parsing a file for hot reloading
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HotReloadDiagnostic {
+    pub message: String,
+
+    /// The 1-indexed line the error starts on
+    pub line: usize,
+
+    /// The 1-indexed column the error starts on
+    pub column: usize,
+
+    /// The line of source the error points at, trimmed of surrounding whitespace
+    #[serde(default)]
+    pub snippet: String,
+
+    /// What kind of failure this is. Io errors don't point at any source, so their line and column are 0
+    #[serde(default)]
+    pub category: DiagnosticCategory,
+}
+
+/// The kind of failure a [`HotReloadDiagnostic`] describes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagnosticCategory {
+    /// The file or an rsx block in it failed to parse
+    #[default]
+    ParseError,
+
+    /// The file couldn't be read, for example because it was removed mid-save or isn't valid UTF-8
+    IoError,
 }
 
 #[test]
@@ -67,3 +210,96 @@ fn serialize_client_msg() {
         r#"{"Log":{"level":"info","messages":["hello world"]}}"#
     );
 }
+
+#[test]
+fn serialize_pause_and_resume() {
+    assert_eq!(
+        serde_json::to_string(&ClientMsg::Pause).unwrap(),
+        r#""Pause""#
+    );
+    assert_eq!(
+        serde_json::from_str::<ClientMsg>(r#""Resume""#).unwrap(),
+        ClientMsg::Resume
+    );
+}
+
+#[test]
+fn serialize_hot_reload_assets() {
+    let msg = DevserverMsg::HotReload(HotReloadMsg {
+        templates: vec![],
+        assets: vec![PathBuf::from("assets/main.css")],
+        unknown_files: vec![],
+        cleared_templates: vec!["src/main.rs:10:5:0".to_string()],
+        asset_details: vec![HotReloadAsset {
+            path: PathBuf::from("main.css"),
+            size: 42,
+            hash: Some("deadbeef".to_string()),
+        }],
+    });
+
+    let json = serde_json::to_string(&msg).unwrap();
+    assert_eq!(serde_json::from_str::<DevserverMsg>(&json).unwrap(), msg);
+}
+
+#[test]
+fn serialize_diagnostics() {
+    let msg = DevserverMsg::Diagnostics {
+        file: PathBuf::from("src/main.rs"),
+        errors: vec![HotReloadDiagnostic {
+            message: "expected `,`".to_string(),
+            line: 12,
+            column: 5,
+            snippet: "div { class: \"a\" \"b\" }".to_string(),
+            category: DiagnosticCategory::ParseError,
+        }],
+    };
+
+    let json = serde_json::to_string(&msg).unwrap();
+    assert_eq!(serde_json::from_str::<DevserverMsg>(&json).unwrap(), msg);
+}
+
+#[test]
+fn deserialize_diagnostics_without_spans() {
+    let json = r#"{"Diagnostics":{"file":"src/main.rs","errors":[{"message":"expected `,`","line":12,"column":5}]}}"#;
+    let DevserverMsg::Diagnostics { errors, .. } =
+        serde_json::from_str::<DevserverMsg>(json).unwrap()
+    else {
+        panic!("expected a diagnostics message");
+    };
+    assert_eq!(errors[0].snippet, "");
+    assert_eq!(errors[0].category, DiagnosticCategory::ParseError);
+}
+
+#[test]
+fn deserialize_hot_reload_without_cleared_templates() {
+    let json = r#"{"HotReload":{"templates":[],"assets":[],"unknown_files":[]}}"#;
+    let DevserverMsg::HotReload(msg) = serde_json::from_str::<DevserverMsg>(json).unwrap() else {
+        panic!("expected a hot reload message");
+    };
+    assert!(msg.cleared_templates.is_empty());
+}
+
+#[test]
+fn version_check() {
+    let protocol = PROTOCOL_VERSION.to_string();
+    assert_eq!(VersionCheck::new(None, None), VersionCheck::Compatible);
+    assert_eq!(
+        VersionCheck::new(Some(&protocol), Some(VERSION)),
+        VersionCheck::Compatible
+    );
+    assert_eq!(
+        VersionCheck::new(Some(&protocol), Some("0.0.1")),
+        VersionCheck::VersionMismatch {
+            peer: "0.0.1".to_string()
+        }
+    );
+
+    let bumped = (PROTOCOL_VERSION + 1).to_string();
+    let check = VersionCheck::new(Some(&bumped), Some("0.0.1"));
+    assert!(!check.is_compatible());
+    assert_eq!(
+        check.to_string(),
+        format!("hot reload disabled: version mismatch (client {VERSION}, server 0.0.1)")
+    );
+    assert!(!VersionCheck::new(Some(&bumped), None).is_compatible());
+}
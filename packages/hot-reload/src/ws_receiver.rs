@@ -1,4 +1,6 @@
-use crate::DevserverMsg;
+use crate::{
+    DevserverMsg, VersionCheck, PROTOCOL_VERSION, PROTOCOL_VERSION_HEADER, VERSION, VERSION_HEADER,
+};
 use futures_util::{SinkExt, StreamExt};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{
@@ -6,9 +8,22 @@ use tokio_tungstenite::{
     MaybeTlsStream, WebSocketStream,
 };
 
-pub fn connect(mut callback: impl FnMut(DevserverMsg) + Send + 'static) {
+/// Connect to the devserver the CLI launched this application with, calling `callback` for every message it sends.
+///
+/// Does nothing if the application was not run with the CLI.
+pub fn connect(callback: impl FnMut(DevserverMsg) + Send + 'static) {
+    let Some(cli_args) = dioxus_cli_config::RuntimeCLIArguments::from_cli() else {
+        return;
+    };
+    connect_at(cli_endpoint(cli_args.cli_address()), callback);
+}
+
+/// Connect to a devserver websocket at a custom endpoint, e.g. `ws://127.0.0.1:8080/_dioxus`.
+///
+/// This is useful when running several devservers side by side.
+pub fn connect_at(endpoint: String, mut callback: impl FnMut(DevserverMsg) + Send + 'static) {
     tokio::spawn(async move {
-        let Some(Ok(mut recv)) = NativeReceiver::create_from_cli().await else {
+        let Ok(mut recv) = NativeReceiver::create(endpoint).await else {
             return;
         };
         while let Some(msg) = recv.next().await {
@@ -20,31 +35,56 @@ pub fn connect(mut callback: impl FnMut(DevserverMsg) + Send + 'static) {
     });
 }
 
+/// The websocket endpoint the devserver exposes at the given address
+fn cli_endpoint(addr: std::net::SocketAddr) -> String {
+    format!("ws://{addr}/_dioxus")
+}
+
 /// A receiver for messages from the devserver
 ///
 /// Calling `next` will watch the channel for the next valid message from the devserver
 pub struct NativeReceiver {
     socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    version: VersionCheck,
 }
 
 impl NativeReceiver {
     /// Connect to the devserver
     async fn create(url: String) -> TtResult<Self> {
-        let (socket, _ws) = tokio_tungstenite::connect_async(&url).await?;
-        Ok(Self { socket })
+        // Tell the devserver which version we are so it can warn about mismatches on its side too
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let url = format!("{url}{separator}protocol={PROTOCOL_VERSION}&version={VERSION}");
+        let (socket, response) = tokio_tungstenite::connect_async(&url).await?;
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let version = VersionCheck::new(header(PROTOCOL_VERSION_HEADER), header(VERSION_HEADER));
+        if version != VersionCheck::Compatible {
+            tracing::warn!("{version}");
+        }
+
+        Ok(Self { socket, version })
     }
 
     /// Connect to the devserver with an address from the CLI. Returns None if the current application was not run with the CLI
     pub async fn create_from_cli() -> Option<TtResult<Self>> {
         let cli_args = dioxus_cli_config::RuntimeCLIArguments::from_cli()?;
-        let addr = cli_args.cli_address();
-        Some(Self::create(format!("ws://{addr}/_dioxus")).await)
+        Some(Self::create(cli_endpoint(cli_args.cli_address())).await)
     }
 
     /// Wait for the next message from the devserver
     ///
     /// Returns None when the connection is closed or socket.next() returns None
     pub async fn next(&mut self) -> Option<TtResult<DevserverMsg>> {
+        // We can't make sense of anything a devserver with a different protocol sends
+        if !self.version.is_compatible() {
+            return None;
+        }
+
         loop {
             let res = self.socket.next().await?;
 
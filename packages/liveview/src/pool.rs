@@ -221,6 +221,18 @@ pub async fn run(mut vdom: VirtualDom, ws: impl LiveViewSocket) -> Result<(), Li
                     DevserverMsg::Shutdown => {
                         std::process::exit(0);
                     },
+                    DevserverMsg::Diagnostics { file, errors } => {
+                        // There's no overlay to show these in yet, so log them for the developer
+                        for error in errors {
+                            tracing::error!(
+                                "Failed to hot reload {}:{}:{}: {}",
+                                file.display(),
+                                error.line,
+                                error.column,
+                                error.message
+                            );
+                        }
+                    },
                     DevserverMsg::FullReloadCommand
                     | DevserverMsg::FullReloadStart
                     | DevserverMsg::FullReloadFailed => {
@@ -3,11 +3,14 @@
 //! This sets up a websocket connection to the devserver and handles messages from it.
 //! We also set up a little recursive timer that will attempt to reconnect if the connection is lost.
 
+use std::cell::RefCell;
 use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use dioxus_core::ScopeId;
-use dioxus_hot_reload::{DevserverMsg, HotReloadMsg};
+use dioxus_hot_reload::{DevserverMsg, HotReloadMsg, PROTOCOL_VERSION, VERSION};
 use dioxus_html::prelude::eval;
 use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use js_sys::JsString;
@@ -19,6 +22,14 @@ const POLL_INTERVAL_MIN: i32 = 250;
 const POLL_INTERVAL_MAX: i32 = 4000;
 const POLL_INTERVAL_SCALE_FACTOR: i32 = 2;
 
+/// Whether we've already logged a devserver message we couldn't parse
+static PARSE_ERROR_REPORTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// The file the toast on screen is reporting a hot reload error for, if it is a diagnostic toast
+    static DIAGNOSTIC_TOAST_FILE: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
 /// Amount of time that toats should be displayed.
 const TOAST_TIMEOUT: Duration = Duration::from_secs(5);
 
@@ -36,8 +47,10 @@ fn make_ws(tx: UnboundedSender<HotReloadMsg>, poll_interval: i32, reload: bool)
     // Get the location of the devserver, using the current location plus the /_dioxus path
     // The idea here being that the devserver is always located on the /_dioxus behind a proxy
     let location = web_sys::window().unwrap().location();
+    // We tell the devserver our version so it can warn about mismatches. Browsers don't let us read the
+    // headers it answers with, so we find out about mismatches from messages we can't parse instead.
     let url = format!(
-        "{protocol}//{host}/_dioxus",
+        "{protocol}//{host}/_dioxus?protocol={PROTOCOL_VERSION}&version={VERSION}",
         protocol = match location.protocol().unwrap() {
             prot if prot == "https:" => "wss:",
             _ => "ws:",
@@ -87,6 +100,40 @@ fn make_ws(tx: UnboundedSender<HotReloadMsg>, poll_interval: i32, reload: bool)
                     false,
                 ),
 
+                // The devserver is telling us that some rsx failed to parse
+                Ok(DevserverMsg::Diagnostics { file, errors }) => {
+                    // The file was fixed, so take down the error we showed for it
+                    if errors.is_empty() {
+                        let showing = DIAGNOSTIC_TOAST_FILE
+                            .with(|shown| shown.borrow().as_ref() == Some(&file));
+                        if showing {
+                            close_toast();
+                        }
+                    }
+
+                    if let Some(error) = errors.first() {
+                        let mut message = format!(
+                            "{}:{}:{}: {}",
+                            file.display(),
+                            error.line,
+                            error.column,
+                            error.message
+                        );
+                        if !error.snippet.is_empty() {
+                            message.push('\n');
+                            message.push_str(&error.snippet);
+                        }
+                        show_toast(
+                            "Failed to hot reload.",
+                            &message,
+                            ToastLevel::Error,
+                            TOAST_TIMEOUT,
+                            false,
+                        );
+                        DIAGNOSTIC_TOAST_FILE.with(|shown| *shown.borrow_mut() = Some(file));
+                    }
+                }
+
                 // The devserver is telling us to reload the whole page
                 Ok(DevserverMsg::FullReloadCommand) => {
                     show_toast(
@@ -99,9 +146,14 @@ fn make_ws(tx: UnboundedSender<HotReloadMsg>, poll_interval: i32, reload: bool)
                     window().unwrap().location().reload().unwrap()
                 }
 
-                Err(e) => web_sys::console::error_1(
-                    &format!("Error parsing devserver message: {}", e).into(),
-                ),
+                // Only complain once - if one message fails to parse, the rest likely will too
+                Err(e) => {
+                    if !PARSE_ERROR_REPORTED.swap(true, Ordering::Relaxed) {
+                        web_sys::console::error_1(
+                            &format!("Error parsing devserver message: {e}. Is dx the same version as dioxus ({VERSION})?").into(),
+                        )
+                    }
+                }
             }
         })
         .into_js_value()
@@ -197,22 +249,44 @@ fn show_toast(
 ) {
     let as_ms = duration.as_millis();
 
+    // Whatever toast was on screen is replaced by this one
+    DIAGNOSTIC_TOAST_FILE.with(|shown| shown.borrow_mut().take());
+
     let js_fn_name = match after_reload {
         true => "scheduleDXToast",
         false => "showDXToast",
     };
 
+    // Quote the text as JSON string literals so quotes, backslashes and newlines survive the eval
+    let header_text = serde_json::to_string(header_text).unwrap_or_else(|_| "\"\"".into());
+    let message = serde_json::to_string(message).unwrap_or_else(|_| "\"\"".into());
+
     ScopeId::ROOT.in_runtime(|| {
         eval(&format!(
             r#"
             if (typeof {js_fn_name} !== "undefined") {{
-                {js_fn_name}("{header_text}", "{message}", "{level}", {as_ms});
+                {js_fn_name}({header_text}, {message}, "{level}", {as_ms});
             }}
             "#,
         ));
     });
 }
 
+/// Closes the toast that is currently displayed, if any.
+fn close_toast() {
+    DIAGNOSTIC_TOAST_FILE.with(|shown| shown.borrow_mut().take());
+
+    ScopeId::ROOT.in_runtime(|| {
+        eval(
+            r#"
+            if (typeof closeDXToast !== "undefined") {
+                closeDXToast();
+            }
+            "#,
+        );
+    });
+}
+
 /// Force a hotreload of the assets on this page by walking them and changing their URLs to include
 /// some extra entropy.
 ///